@@ -1,18 +1,27 @@
 use crate::{
+    backlog::{BacklogItem, BacklogSort},
+    cache::{self, Cache},
+    coverart::{Cover, CoverFetcher, CoverPlacement, Graphics},
     database,
     musicbrainz::*,
-    rating::{Rate, Rated},
-    ui::StatefulList,
-    utils::get_database_path,
+    rating::{Filter, Rate, Rated, RatingEvent, RatingSort},
+    stats::{self, ArtistSummary, Stats},
+    ui::{FuzzyFilter, Searchable, StatefulList},
+    utils::{join_tags, parse_tags},
 };
 use anyhow::Result;
-use futures::executor;
-use musicbrainz_rs::entity::release_group::{ReleaseGroupPrimaryType, ReleaseGroupSecondaryType};
+use image::RgbaImage;
+use musicbrainz_rs::{
+    Error,
+    entity::release_group::{ReleaseGroupPrimaryType, ReleaseGroupSecondaryType},
+};
+use ratatui::text::Line;
 use rusqlite::Connection;
-use serde::Serialize;
-use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display};
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
 
-#[derive(Default, PartialEq, Eq, Clone)]
+#[derive(Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct ReleaseType {
     primary: Option<ReleaseGroupPrimaryType>,
     secondary: Vec<ReleaseGroupSecondaryType>,
@@ -25,6 +34,78 @@ impl ReleaseType {
     ) -> Self {
         Self { primary, secondary }
     }
+
+    pub fn from_columns(primary: Option<String>, secondary: Option<String>) -> Self {
+        Self {
+            primary: primary.and_then(|primary| serde_json::from_value(primary.into()).ok()),
+            secondary: secondary
+                .and_then(|secondary| serde_json::from_str(&secondary).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Parses the `Album + Live` form that release types are displayed in.
+    pub fn parse(value: &str) -> Self {
+        let mut release_type = Self::default();
+
+        for name in value
+            .split('+')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match serde_json::from_value(name.into()) {
+                Ok(ReleaseGroupPrimaryType::UnrecognizedReleaseGroupPrimaryType) | Err(_) => {
+                    if let Ok(secondary) = serde_json::from_value(name.into()) {
+                        release_type.secondary.push(secondary);
+                    }
+                }
+                Ok(primary) => release_type.primary = Some(primary),
+            }
+        }
+
+        release_type
+    }
+
+    pub fn primary_type(&self) -> Option<String> {
+        self.primary.as_ref().map(release_type_to_string)
+    }
+
+    pub fn secondary_types(&self) -> String {
+        serde_json::to_string(&self.secondary).unwrap()
+    }
+
+    pub fn secondary_type_names(&self) -> Vec<String> {
+        self.secondary.iter().map(release_type_to_string).collect()
+    }
+
+    /// Whether the primary type or any of the secondary types has the given
+    /// name, ignoring case.
+    pub fn has_type(&self, name: &str) -> bool {
+        self.primary
+            .iter()
+            .map(release_type_to_string)
+            .chain(self.secondary.iter().map(release_type_to_string))
+            .any(|release_type| release_type.eq_ignore_ascii_case(name))
+    }
+
+    /// Orders albums before EPs and singles, and plain releases before the ones
+    /// with secondary types such as live albums and compilations.
+    pub fn sort_key(&self) -> (u8, Vec<String>) {
+        let primary = match &self.primary {
+            Some(ReleaseGroupPrimaryType::Album) => 0,
+            Some(ReleaseGroupPrimaryType::Ep) => 1,
+            Some(ReleaseGroupPrimaryType::Single) => 2,
+            Some(ReleaseGroupPrimaryType::Broadcast) => 3,
+            Some(ReleaseGroupPrimaryType::Other) => 4,
+            Some(_) => 5,
+            None => 6,
+        };
+
+        (
+            primary,
+            self.secondary.iter().map(release_type_to_string).collect(),
+        )
+    }
 }
 
 impl Display for ReleaseType {
@@ -53,73 +134,569 @@ pub enum ListItemType {
     Release(Release),
 }
 
+impl Searchable for ListItemType {
+    fn search_text(&self) -> String {
+        match self {
+            ListItemType::ReleaseType(release_type) => release_type.to_string(),
+            ListItemType::Release(release) => release.search_text(),
+        }
+    }
+
+    fn is_header(&self) -> bool {
+        matches!(self, ListItemType::ReleaseType(_))
+    }
+}
+
+/// Results of the requests that run in the background, sent back to the UI
+/// thread.
+pub enum Message {
+    SearchResults {
+        query: String,
+        result: Result<Vec<ArtistSearchResult>, Error>,
+    },
+    Tracks {
+        release_id: String,
+        result: Result<Vec<Track>, Error>,
+    },
+    ReleasesPage {
+        artist_id: String,
+        result: Result<ReleasesPage, Error>,
+    },
+    Cover {
+        release_id: String,
+        result: anyhow::Result<Option<RgbaImage>>,
+    },
+    Details {
+        release_id: String,
+        result: Result<ReleaseDetails, Error>,
+    },
+}
+
+/// The MusicBrainz details of a release shown in the detail panel.
+pub enum Details {
+    Loading,
+    Loaded {
+        info: ReleaseDetails,
+        stale: bool,
+    },
+    /// Not cached while offline, or the request failed.
+    Unavailable,
+}
+
+/// The number of queries kept in the search history.
+const SEARCH_HISTORY_SIZE: usize = 50;
+
+/// The search input at the bottom of the screen, along with the queries that
+/// were submitted from it.
+#[derive(Default)]
+pub struct SearchPrompt {
+    pub query: String,
+    pub active: bool,
+    history: Vec<String>,
+    history_index: Option<usize>,
+}
+
+impl SearchPrompt {
+    fn submit(&mut self) -> String {
+        let query = std::mem::take(&mut self.query).trim().to_string();
+
+        self.active = false;
+        self.history_index = None;
+        self.history.retain(|previous| *previous != query);
+        self.history.push(query.clone());
+
+        if self.history.len() > SEARCH_HISTORY_SIZE {
+            self.history.remove(0);
+        }
+
+        query
+    }
+
+    pub fn previous_query(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let idx = match self.history_index {
+            Some(idx) => idx.saturating_sub(1),
+            None => self.history.len() - 1,
+        };
+
+        self.history_index = Some(idx);
+        self.query = self.history[idx].clone();
+    }
+
+    pub fn next_query(&mut self) {
+        let Some(idx) = self.history_index else {
+            return;
+        };
+
+        if idx + 1 < self.history.len() {
+            self.history_index = Some(idx + 1);
+            self.query = self.history[idx + 1].clone();
+        } else {
+            self.history_index = None;
+            self.query.clear();
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    Search,
+    Ratings,
+    Backlog,
+    Stats,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 4] = [Tab::Search, Tab::Ratings, Tab::Backlog, Tab::Stats];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Tab::Search => "Search",
+            Tab::Ratings => "Ratings",
+            Tab::Backlog => "Backlog",
+            Tab::Stats => "Stats",
+        }
+    }
+
+    fn index(self) -> usize {
+        Tab::ALL.iter().position(|tab| *tab == self).unwrap()
+    }
+}
+
+pub struct PendingReleases {
+    pub fetched: usize,
+    pub count: usize,
+}
+
+/// The release groups of an artist, opened on top of the tab it was opened
+/// from.
+pub struct Discography {
+    pub artist_id: String,
+    pub artist_name: String,
+    pub tab: Tab,
+    pub list: StatefulList<ListItemType>,
+    pub pending: Option<PendingReleases>,
+    pub stale: bool,
+    /// The release to select once it arrives.
+    focus: Option<String>,
+    pub tracklist: Option<Tracklist>,
+    /// Whether the releases are ranked by rating instead of grouped by type.
+    pub ranked: bool,
+}
+
+impl Discography {
+    fn releases(&self) -> impl Iterator<Item = &Release> {
+        self.list.iter_all().filter_map(|item| match item {
+            ListItemType::Release(release) => Some(release),
+            ListItemType::ReleaseType(_) => None,
+        })
+    }
+
+    fn releases_mut(&mut self) -> impl Iterator<Item = &mut Release> {
+        self.list.iter_all_mut().filter_map(|item| match item {
+            ListItemType::Release(release) => Some(release),
+            ListItemType::ReleaseType(_) => None,
+        })
+    }
+
+    pub fn summary(&self) -> ArtistSummary {
+        ArtistSummary::compute(self.releases())
+    }
+
+    /// Adds the new releases and lays the list out again, grouped by type or
+    /// ranked by rating. The selection stays on the same release.
+    fn arrange(&mut self, new_releases: Vec<Release>) {
+        let list = &mut self.list;
+
+        let selected_id = match list.get_selected() {
+            Some(ListItemType::Release(release)) => Some(release.id.clone()),
+            _ => self.focus.clone(),
+        };
+
+        let mut releases = list
+            .take_items()
+            .into_iter()
+            .filter_map(|item| match item {
+                ListItemType::Release(release) => Some(release),
+                ListItemType::ReleaseType(_) => None,
+            })
+            .chain(new_releases)
+            .collect::<Vec<Release>>();
+
+        if self.ranked {
+            rank_releases(&mut releases);
+            list.set_items(releases.into_iter().map(ListItemType::Release).collect());
+        } else {
+            sort_releases(&mut releases);
+            list.set_items(insert_headers(releases));
+        }
+
+        let selected = list.items.iter().position(|item| {
+            matches!(item, ListItemType::Release(release) if Some(&release.id) == selected_id.as_ref())
+        });
+
+        match selected {
+            Some(idx) => {
+                list.select(Some(idx));
+                self.focus = None;
+            }
+            None => list.select(
+                list.items
+                    .iter()
+                    .position(|item| matches!(item, ListItemType::Release(_))),
+            ),
+        }
+    }
+}
+
+/// The tracks of a release group, opened from the discography of its artist.
+pub struct Tracklist {
+    pub release_id: String,
+    pub title: String,
+    pub list: StatefulList<Track>,
+    pub loading: bool,
+    pub stale: bool,
+}
+
+/// A rating that is waiting for its removal to be confirmed.
+pub struct PendingUnrate {
+    pub release_id: String,
+    /// The recording whose track rating is removed instead of the rating of
+    /// the release.
+    pub recording_id: Option<String>,
+    pub description: String,
+}
+
+/// The review of a rated release that is being written.
+pub struct ReviewEditor {
+    pub release_id: String,
+    pub description: String,
+    pub text: String,
+}
+
+/// Our tags of a rated or backlogged release, written as `road trip, revisit`.
+pub struct TagEditor {
+    pub release_id: String,
+    pub description: String,
+    pub text: String,
+    /// Tags that are already in use, suggested while typing.
+    known: Vec<String>,
+}
+
+impl TagEditor {
+    /// The first known tag that starts like the tag being typed, unless it's
+    /// entered already, along with where the typed part starts.
+    fn suggestion(&self) -> Option<(usize, &str)> {
+        let (entered, start) = match self.text.rfind(',') {
+            Some(idx) => (parse_tags(&self.text[..idx]), idx + 1),
+            None => (Vec::new(), 0),
+        };
+        let start = start + self.text[start..].len() - self.text[start..].trim_start().len();
+        let typed = self.text[start..].to_lowercase();
+
+        if typed.is_empty() {
+            return None;
+        }
+
+        self.known
+            .iter()
+            .filter(|tag| !entered.iter().any(|other| other.eq_ignore_ascii_case(tag)))
+            .find(|tag| tag.to_lowercase().starts_with(&typed) && tag.len() > typed.len())
+            .map(|tag| (start, tag.as_str()))
+    }
+
+    /// The rest of the suggested tag, shown after what has been typed.
+    pub fn completion(&self) -> Option<&str> {
+        let (start, tag) = self.suggestion()?;
+        let typed_len = self.text[start..].chars().count();
+
+        tag.char_indices()
+            .nth(typed_len)
+            .map(|(idx, _)| &tag[idx..])
+    }
+
+    /// Replaces the tag being typed with the suggested one, in the case it
+    /// was first written in.
+    pub fn complete(&mut self) {
+        if let Some((start, tag)) = self.suggestion() {
+            let tag = tag.to_string();
+
+            self.text.truncate(start);
+            self.text.push_str(&tag);
+            self.text.push_str(", ");
+        }
+    }
+}
+
 pub struct App {
+    pub tab: Tab,
     pub search_results: Option<StatefulList<ArtistSearchResult>>,
-    pub releases: Option<StatefulList<ListItemType>>,
-    pub rated_list: Option<StatefulList<Rated>>,
+    pub releases: Option<Discography>,
+    pub rated_list: StatefulList<Rated>,
+    /// The number of rated releases, including the filtered out ones.
+    pub rated_total: usize,
+    pub rating_sort: RatingSort,
+    pub rating_filter: Filter,
+    pub stats: Stats,
+    /// The filter being written for the rated list.
+    pub filter_prompt: Option<String>,
+    /// The fuzzy query being typed for the focused list.
+    pub find_prompt: Option<String>,
+    pub backlog: StatefulList<BacklogItem>,
+    pub backlog_sort: BacklogSort,
     pub currently_rating: bool,
+    pub pending_unrate: Option<PendingUnrate>,
+    pub show_history: bool,
+    /// Rating changes of the selected release, loaded while the history panel
+    /// is shown.
+    pub history: Vec<RatingEvent>,
+    /// Review of the selected release, loaded along with its history.
+    pub review: Option<String>,
+    pub review_editor: Option<ReviewEditor>,
+    /// Our tags of the selected release, loaded along with its history.
+    pub tags: Vec<String>,
+    /// Genres of the artist of the selected release if it's rated, loaded
+    /// along with its history.
+    pub artist_genres: Vec<String>,
+    /// The release the history, review and tags were loaded for. They're only
+    /// loaded again once another release is selected or the ratings change.
+    history_release: Option<String>,
+    pub tag_editor: Option<TagEditor>,
+    pub show_details: bool,
+    /// Details of the releases that were selected while the detail panel was
+    /// shown.
+    pub details: HashMap<String, Details>,
+    /// Covers of the releases of the open discography.
+    pub covers: HashMap<String, Cover>,
+    pub graphics: Graphics,
+    /// Where the cover was left to be drawn with terminal graphics in the last
+    /// frame.
+    pub cover_placement: Option<CoverPlacement>,
+    /// The cover drawn with half blocks, kept until another cover is shown or
+    /// the panel is resized since scaling the image is slow.
+    pub half_block_cover: Option<(CoverPlacement, Vec<Line<'static>>)>,
+    pub search_prompt: SearchPrompt,
+    pub pending_search: Option<String>,
+    pub status: Option<String>,
+    pub search_stale: bool,
+    pub spinner_frame: usize,
     previous_rating: Option<u8>,
     conn: Connection,
+    pub cache: Cache,
+    tx: UnboundedSender<Message>,
+    task: Option<JoinHandle<()>>,
+    tracks_task: Option<JoinHandle<()>>,
+    cover_fetcher: CoverFetcher,
+    /// The cover being loaded, along with its release.
+    cover_task: Option<(String, JoinHandle<()>)>,
+    /// The details being fetched, along with their release.
+    details_task: Option<(String, JoinHandle<()>)>,
 }
 
 impl App {
-    pub fn search(search_query: &str) -> Result<Self> {
-        let search_results = executor::block_on(search_artist(search_query)).unwrap_or_default();
+    pub fn new(
+        cache: Cache,
+        cover_fetcher: CoverFetcher,
+        graphics: Graphics,
+        tx: UnboundedSender<Message>,
+    ) -> Result<Self> {
+        let conn = database::open_db()?;
 
-        if search_results.is_empty() {
-            println!("No artists were found with the given query.");
-            std::process::exit(1);
-        }
+        let rated = database::get_every_rating(&conn)?;
 
         let app = App {
-            search_results: Some(StatefulList::with_items(search_results)),
+            tab: Tab::Search,
+            search_results: None,
             releases: None,
-            rated_list: None,
+            rated_total: rated.len(),
+            stats: Stats::compute(&rated, stats::DEFAULT_MIN_RATINGS),
+            rated_list: StatefulList::with_items(rated),
+            rating_sort: RatingSort::default(),
+            rating_filter: Filter::default(),
+            filter_prompt: None,
+            find_prompt: None,
+            backlog: StatefulList::with_items(database::get_backlog(&conn)?),
+            backlog_sort: BacklogSort::default(),
             currently_rating: false,
+            pending_unrate: None,
+            show_history: false,
+            history: Vec::new(),
+            review: None,
+            review_editor: None,
+            tags: Vec::new(),
+            artist_genres: Vec::new(),
+            history_release: None,
+            tag_editor: None,
+            show_details: false,
+            details: HashMap::new(),
+            covers: HashMap::new(),
+            graphics,
+            cover_placement: None,
+            half_block_cover: None,
+            search_prompt: SearchPrompt::default(),
+            pending_search: None,
+            status: None,
+            search_stale: false,
+            spinner_frame: 0,
             previous_rating: None,
-            conn: Connection::open(get_database_path()?)?,
+            conn,
+            cache,
+            tx,
+            task: None,
+            tracks_task: None,
+            cover_fetcher,
+            cover_task: None,
+            details_task: None,
         };
 
-        database::initialize_db(&app.conn)?;
-
         Ok(app)
     }
 
-    pub fn list_rated() -> Result<Self> {
-        let conn = Connection::open(get_database_path()?)?;
+    pub fn select_tab(&mut self, tab: Tab) {
+        if !self.currently_rating {
+            self.tab = tab;
+        }
+    }
 
-        let app = App {
-            search_results: None,
-            releases: None,
-            rated_list: Some(StatefulList::with_items(database::get_every_rating(&conn)?)),
-            currently_rating: false,
-            previous_rating: None,
-            conn,
-        };
+    pub fn next_tab(&mut self) {
+        self.select_tab(Tab::ALL[(self.tab.index() + 1) % Tab::ALL.len()]);
+    }
 
-        Ok(app)
+    pub fn previous_tab(&mut self) {
+        self.select_tab(Tab::ALL[(self.tab.index() + Tab::ALL.len() - 1) % Tab::ALL.len()]);
     }
 
-    fn get_selected_release(&self) -> Option<&Release> {
-        if let Some(releases) = &self.releases {
-            if let Some(ListItemType::Release(release)) = releases.get_selected() {
-                return Some(release);
+    /// The discography opened from the current tab, if there is one.
+    pub fn discography(&self) -> Option<&Discography> {
+        self.releases
+            .as_ref()
+            .filter(|discography| discography.tab == self.tab)
+    }
+
+    fn discography_mut(&mut self) -> Option<&mut Discography> {
+        self.releases
+            .as_mut()
+            .filter(|discography| discography.tab == self.tab)
+    }
+
+    pub fn open_search_prompt(&mut self) {
+        if !self.currently_rating {
+            self.search_prompt.active = true;
+        }
+    }
+
+    pub fn close_search_prompt(&mut self) {
+        self.search_prompt.active = false;
+        self.search_prompt.query.clear();
+    }
+
+    pub fn submit_search(&mut self) -> Result<()> {
+        if self.search_prompt.query.trim().is_empty() {
+            self.close_search_prompt();
+            return Ok(());
+        }
+
+        let query = self.search_prompt.submit();
+        self.search(query)
+    }
+
+    pub fn search(&mut self, query: String) -> Result<()> {
+        self.cancel_fetch();
+        self.tab = Tab::Search;
+
+        let cached = self
+            .cache
+            .get::<Vec<ArtistSearchResult>>(&cache::search_key(&query))?;
+
+        match cached {
+            Some(cached) if !cached.stale || self.cache.offline => {
+                self.show_search_results(&query, cached.value, cached.stale);
+            }
+            _ if self.cache.offline => self.show_search_results(&query, Vec::new(), false),
+            _ => {
+                let tx = self.tx.clone();
+                self.pending_search = Some(query.clone());
+
+                self.task = Some(tokio::spawn(async move {
+                    let result = search_artist(&query).await;
+                    let _ = tx.send(Message::SearchResults { query, result });
+                }));
             }
         }
 
+        Ok(())
+    }
+
+    fn show_search_results(&mut self, query: &str, results: Vec<ArtistSearchResult>, stale: bool) {
+        if results.is_empty() {
+            self.status = Some(if self.cache.offline {
+                format!("No cached artists were found for \"{query}\"")
+            } else {
+                format!("No artists were found for \"{query}\"")
+            });
+            return;
+        }
+
+        if self.discography().is_some() {
+            self.releases = None;
+        }
+
+        self.search_stale = stale;
+        self.search_results = Some(StatefulList::with_items(results));
+    }
+
+    fn get_selected_release(&self) -> Option<&Release> {
+        if let Some(discography) = self.discography()
+            && let Some(ListItemType::Release(release)) = discography.list.get_selected()
+        {
+            return Some(release);
+        }
+
         None
     }
 
     fn get_mut_selected_release(&mut self) -> Option<&mut Release> {
-        if let Some(releases) = &mut self.releases {
-            if let Some(ListItemType::Release(release)) = releases.get_mut_selected() {
-                return Some(release);
-            }
+        if let Some(discography) = self.discography_mut()
+            && let Some(ListItemType::Release(release)) = discography.list.get_mut_selected()
+        {
+            return Some(release);
         }
 
         None
     }
 
+    /// The release or rated list entry that is currently selected.
+    fn get_mut_selected_rateable(&mut self) -> Option<&mut dyn Rate> {
+        if self.tracklist().is_some() {
+            return self
+                .tracklist_mut()
+                .and_then(|tracklist| tracklist.list.get_mut_selected())
+                .map(|track| track as &mut dyn Rate);
+        }
+
+        if self.get_selected_release().is_some() {
+            return self
+                .get_mut_selected_release()
+                .map(|release| release as &mut dyn Rate);
+        }
+
+        match self.tab {
+            _ if self.discography().is_some() => None,
+            Tab::Ratings => self
+                .rated_list
+                .get_mut_selected()
+                .map(|item| item as &mut dyn Rate),
+            Tab::Backlog => self
+                .backlog
+                .get_mut_selected()
+                .map(|item| item as &mut dyn Rate),
+            Tab::Search | Tab::Stats => None,
+        }
+    }
+
     pub fn get_selected_artist(&self) -> Option<&ArtistSearchResult> {
         self.search_results
             .as_ref()
@@ -131,16 +708,26 @@ impl App {
             return;
         }
 
-        if let Some(releases) = &mut self.releases {
+        if let Some(tracklist) = self.tracklist_mut() {
+            tracklist.list.next();
+        } else if let Some(discography) = self.discography_mut() {
+            let releases = &mut discography.list;
             releases.next();
 
             if let Some(ListItemType::ReleaseType(_)) = releases.get_selected() {
                 releases.next();
             }
-        } else if let Some(results) = &mut self.search_results {
-            results.next();
-        } else if let Some(rated) = &mut self.rated_list {
-            rated.next();
+        } else {
+            match self.tab {
+                Tab::Search => {
+                    if let Some(results) = &mut self.search_results {
+                        results.next();
+                    }
+                }
+                Tab::Ratings => self.rated_list.next(),
+                Tab::Backlog => self.backlog.next(),
+                Tab::Stats => (),
+            }
         }
     }
 
@@ -149,165 +736,1321 @@ impl App {
             return;
         }
 
-        if let Some(releases) = &mut self.releases {
+        if let Some(tracklist) = self.tracklist_mut() {
+            tracklist.list.previous();
+        } else if let Some(discography) = self.discography_mut() {
+            let releases = &mut discography.list;
             releases.previous();
 
             if let Some(ListItemType::ReleaseType(_)) = releases.get_selected() {
                 releases.previous();
             }
-        } else if let Some(results) = &mut self.search_results {
-            results.previous();
-        } else if let Some(rated) = &mut self.rated_list {
-            rated.previous();
+        } else {
+            match self.tab {
+                Tab::Search => {
+                    if let Some(results) = &mut self.search_results {
+                        results.previous();
+                    }
+                }
+                Tab::Ratings => self.rated_list.previous(),
+                Tab::Backlog => self.backlog.previous(),
+                Tab::Stats => (),
+            }
         }
     }
 
     pub fn on_left(&mut self) {
         if self.currently_rating {
-            if let Some(release) = self.get_mut_selected_release() {
-                release.decrease_rating();
-            } else if let Some(list) = &mut self.rated_list {
-                list.get_mut_selected().unwrap().decrease_rating();
+            if let Some(item) = self.get_mut_selected_rateable() {
+                item.decrease_rating();
+            }
+        } else if self.tracklist().is_some() {
+            self.cancel_tracks_fetch();
+
+            if let Some(discography) = self.discography_mut() {
+                discography.tracklist = None;
             }
-        } else if self.releases.is_some() {
-            std::mem::take(&mut self.releases);
+        } else if self.discography().is_some() {
+            self.cancel_fetch();
+            self.releases = None;
         }
     }
 
     pub fn on_right(&mut self) -> Result<()> {
         if self.currently_rating {
-            if let Some(release) = self.get_mut_selected_release() {
-                release.increase_rating();
-            } else if let Some(list) = &mut self.rated_list {
-                list.get_mut_selected().unwrap().increase_rating();
+            if let Some(item) = self.get_mut_selected_rateable() {
+                item.increase_rating();
             }
-        } else if self.releases.is_none()
-            && let Some(artist) = self.get_selected_artist()
-        {
-            if let Ok(Some(mut releases)) = executor::block_on(fetch_releases(&artist.id)) {
-                let ratings = database::get_ratings(&self.conn, &artist.id)?;
-
-                for rating in ratings {
-                    for release in &mut releases {
-                        if release.id == rating.0 {
-                            release.rating = Some(rating.1);
-                        }
+        } else if self.tracklist().is_some() {
+            self.start_rating();
+        } else if self.discography().is_some() {
+            self.open_tracklist()?;
+        } else {
+            match self.tab {
+                Tab::Search => {
+                    if let Some(artist) = self.get_selected_artist() {
+                        let (artist_id, artist_name) = (artist.id.clone(), artist.name.clone());
+                        self.open_discography(artist_id, artist_name, None)?;
                     }
                 }
-
-                self.releases = Some(StatefulList::with_items(insert_headers(releases)));
-                self.releases.as_mut().unwrap().next();
+                Tab::Ratings => {
+                    if let Some(item) = self.rated_list.get_selected() {
+                        let (artist_id, artist_name, release_id) = (
+                            item.artist_id.clone(),
+                            item.artist_name.clone(),
+                            item.release_id.clone(),
+                        );
+                        self.open_discography(artist_id, artist_name, Some(release_id))?;
+                    }
+                }
+                Tab::Backlog => {
+                    if let Some(item) = self.backlog.get_selected() {
+                        let (artist_id, artist_name, release_id) = (
+                            item.artist_id.clone(),
+                            item.artist_name.clone(),
+                            item.release_id.clone(),
+                        );
+                        self.open_discography(artist_id, artist_name, Some(release_id))?;
+                    }
+                }
+                Tab::Stats => (),
             }
-        } else {
-            self.start_rating();
         }
 
         Ok(())
     }
 
-    pub fn start_rating(&mut self) {
-        self.currently_rating = true;
+    fn open_discography(
+        &mut self,
+        artist_id: String,
+        artist_name: String,
+        focus: Option<String>,
+    ) -> Result<()> {
+        self.cancel_fetch();
 
-        if let Some(release) = self.get_selected_release() {
-            self.previous_rating = release.rating;
-            let release = self.get_mut_selected_release().unwrap();
+        let cached = self
+            .cache
+            .get::<Vec<Release>>(&cache::releases_key(&artist_id))?;
 
-            if release.rating.is_none() {
-                release.rating = Some(1);
+        self.covers.clear();
+
+        self.releases = Some(Discography {
+            artist_id: artist_id.clone(),
+            artist_name,
+            tab: self.tab,
+            list: StatefulList::with_items(Vec::new()),
+            pending: None,
+            stale: false,
+            focus,
+            tracklist: None,
+            ranked: false,
+        });
+
+        match cached {
+            Some(cached) if !cached.stale || self.cache.offline => {
+                self.show_releases(cached.value, cached.stale)?;
             }
-        } else if let Some(item) = self
-            .rated_list
-            .as_mut()
-            .and_then(StatefulList::get_mut_selected)
-        {
-            self.previous_rating = item.rating;
+            _ if self.cache.offline => {}
+            _ => {
+                if let Some(discography) = &mut self.releases {
+                    discography.pending = Some(PendingReleases {
+                        fetched: 0,
+                        count: 0,
+                    });
+                }
 
-            if item.rating.is_none() {
-                item.rating = Some(1);
+                self.spawn_releases_page(artist_id, 0);
             }
         }
+
+        Ok(())
     }
 
-    pub fn set_rating(&mut self, rating: u8) {
-        if !self.currently_rating {
-            return;
+    fn show_releases(&mut self, releases: Vec<Release>, stale: bool) -> Result<()> {
+        if let Some(discography) = &mut self.releases {
+            discography.pending = None;
+            discography.stale = stale;
         }
 
-        if let Some(list) = &mut self.rated_list {
-            list.get_mut_selected().unwrap().set_rating(rating);
-        } else if let Some(release) = self.get_mut_selected_release() {
-            release.set_rating(rating);
-        }
+        self.merge_releases(releases)
     }
 
-    pub fn confirm_rating(&mut self) -> Result<()> {
-        if !self.currently_rating {
-            return Ok(());
-        }
-
-        if let Some(artist) = self.get_selected_artist() {
-            let release = self.get_selected_release().unwrap();
+    pub fn is_loading(&self) -> bool {
+        [&self.task, &self.tracks_task]
+            .into_iter()
+            .flatten()
+            .chain(self.details_task.as_ref().map(|(_, task)| task))
+            .any(|task| !task.is_finished())
+    }
 
-            database::add_artist(&self.conn, &artist.id, &artist.name)?;
-            database::add_release(&self.conn, &artist.id, release)?;
-        } else if let Some(list) = &self.rated_list {
-            let item = list.get_selected().unwrap();
-            database::add_release(
-                &self.conn,
-                &item.artist_id,
-                &Release {
-                    id: item.release_id.clone(),
-                    title: item.title.clone(),
-                    rating: item.rating,
-                    ..Release::default()
-                },
-            )?;
-        }
+    pub fn tick(&mut self) {
+        self.spinner_frame = self.spinner_frame.wrapping_add(1);
+    }
 
-        self.currently_rating = false;
+    /// Aborts the running request. An artist's discography is kept open with
+    /// the pages that have already arrived, unless none have.
+    pub fn cancel_fetch(&mut self) -> bool {
+        let cancelled_tracks = self.cancel_tracks_fetch();
 
-        Ok(())
-    }
+        let Some(task) = self.task.take() else {
+            return cancelled_tracks;
+        };
 
-    pub fn abort_rating(&mut self) {
-        if !self.currently_rating {
-            return;
-        }
+        task.abort();
 
-        self.currently_rating = false;
-        let prev = self.previous_rating;
+        self.pending_search = None;
 
-        if let Some(release) = self.get_mut_selected_release() {
-            release.rating = prev;
-        } else if let Some(item) = self
-            .rated_list
-            .as_mut()
-            .and_then(StatefulList::get_mut_selected)
+        if let Some(discography) = &mut self.releases
+            && discography.pending.take().is_some()
+            && discography.list.items.is_empty()
         {
-            item.rating = prev;
+            self.releases = None;
         }
+
+        true
     }
-}
 
-fn insert_headers(releases: Vec<Release>) -> Vec<ListItemType> {
-    let mut releases = releases.into_iter();
-    let mut result = if let Some(release) = releases.next() {
-        vec![
-            ListItemType::ReleaseType(release.group_type.clone()),
-            ListItemType::Release(release),
-        ]
-    } else {
-        return Vec::new();
-    };
+    /// Aborts fetching a tracklist, closing it as it has no tracks yet.
+    fn cancel_tracks_fetch(&mut self) -> bool {
+        let Some(task) = self.tracks_task.take() else {
+            return false;
+        };
 
-    for release in releases {
-        if matches!(result.last(), Some(ListItemType::Release(last_item)) if release.group_type != last_item.group_type)
+        task.abort();
+
+        if let Some(discography) = &mut self.releases
+            && discography
+                .tracklist
+                .as_ref()
+                .is_some_and(|tracklist| tracklist.loading)
         {
-            result.push(ListItemType::ReleaseType(release.group_type.clone()));
+            discography.tracklist = None;
         }
 
-        result.push(ListItemType::Release(release));
+        true
     }
 
-    result
+    fn tracklist(&self) -> Option<&Tracklist> {
+        self.discography()
+            .and_then(|discography| discography.tracklist.as_ref())
+    }
+
+    fn tracklist_mut(&mut self) -> Option<&mut Tracklist> {
+        self.discography_mut()
+            .and_then(|discography| discography.tracklist.as_mut())
+    }
+
+    fn open_tracklist(&mut self) -> Result<()> {
+        let Some(release) = self.get_selected_release() else {
+            return Ok(());
+        };
+
+        let (release_id, title) = (release.id.clone(), release.to_string());
+        let cached = self
+            .cache
+            .get::<Vec<Track>>(&cache::tracks_key(&release_id))?;
+
+        if let Some(discography) = self.discography_mut() {
+            discography.tracklist = Some(Tracklist {
+                release_id: release_id.clone(),
+                title,
+                list: StatefulList::with_items(Vec::new()),
+                loading: false,
+                stale: false,
+            });
+        }
+
+        match cached {
+            Some(cached) if !cached.stale || self.cache.offline => {
+                self.show_tracks(cached.value, cached.stale)?;
+            }
+            _ if self.cache.offline => {}
+            _ => {
+                if let Some(tracklist) = self.tracklist_mut() {
+                    tracklist.loading = true;
+                }
+
+                self.spawn_tracks(release_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn show_tracks(&mut self, mut tracks: Vec<Track>, stale: bool) -> Result<()> {
+        let Some(tracklist) = self
+            .releases
+            .as_mut()
+            .and_then(|discography| discography.tracklist.as_mut())
+        else {
+            return Ok(());
+        };
+
+        for (recording_id, rating) in
+            database::get_track_ratings(&self.conn, &tracklist.release_id)?
+        {
+            for track in &mut tracks {
+                if track.id == recording_id {
+                    track.rating = Some(rating);
+                }
+            }
+        }
+
+        tracklist.list = StatefulList::with_items(tracks);
+        tracklist.loading = false;
+        tracklist.stale = stale;
+
+        Ok(())
+    }
+
+    fn spawn_tracks(&mut self, release_id: String) {
+        let tx = self.tx.clone();
+
+        if let Some(task) = self.tracks_task.take() {
+            task.abort();
+        }
+
+        self.tracks_task = Some(tokio::spawn(async move {
+            let result = fetch_tracks(&release_id).await;
+            let _ = tx.send(Message::Tracks { release_id, result });
+        }));
+    }
+
+    fn spawn_releases_page(&mut self, artist_id: String, offset: usize) {
+        let tx = self.tx.clone();
+
+        self.task = Some(tokio::spawn(async move {
+            let result = fetch_releases_page(&artist_id, offset).await;
+            let _ = tx.send(Message::ReleasesPage { artist_id, result });
+        }));
+    }
+
+    pub fn handle_message(&mut self, message: Message) -> Result<()> {
+        match message {
+            Message::SearchResults { query, result } => {
+                if self.pending_search.as_ref() != Some(&query) {
+                    return Ok(());
+                }
+
+                self.pending_search = None;
+                let key = cache::search_key(&query);
+
+                match result {
+                    Ok(results) => {
+                        self.cache.put(&key, &results)?;
+                        self.show_search_results(&query, results, false);
+                    }
+                    Err(e) => match self.cache.get::<Vec<ArtistSearchResult>>(&key)? {
+                        Some(cached) => self.show_search_results(&query, cached.value, true),
+                        None => self.status = Some(format!("Search failed: {e}")),
+                    },
+                }
+            }
+            Message::Tracks { release_id, result } => {
+                let Some(tracklist) = self
+                    .releases
+                    .as_mut()
+                    .and_then(|discography| discography.tracklist.as_mut())
+                else {
+                    return Ok(());
+                };
+
+                if tracklist.release_id != release_id || !tracklist.loading {
+                    return Ok(());
+                }
+
+                let key = cache::tracks_key(&release_id);
+
+                match result {
+                    Ok(tracks) => {
+                        self.cache.put(&key, &tracks)?;
+                        self.show_tracks(tracks, false)?;
+                    }
+                    Err(e) => match self.cache.get::<Vec<Track>>(&key)? {
+                        Some(cached) => self.show_tracks(cached.value, true)?,
+                        None => {
+                            if let Some(discography) = &mut self.releases {
+                                discography.tracklist = None;
+                            }
+
+                            self.status = Some(format!("Couldn't fetch the tracks: {e}"));
+                        }
+                    },
+                }
+            }
+            Message::Cover { release_id, result } => {
+                if !matches!(self.covers.get(&release_id), Some(Cover::Loading)) {
+                    return Ok(());
+                }
+
+                let cover = match result {
+                    Ok(Some(image)) => Cover::Image(image),
+                    Ok(None) => Cover::Missing,
+                    Err(e) => {
+                        self.status = Some(format!("Couldn't load the cover: {e}"));
+                        Cover::Missing
+                    }
+                };
+
+                self.covers.insert(release_id, cover);
+            }
+            Message::Details { release_id, result } => {
+                if !matches!(self.details.get(&release_id), Some(Details::Loading)) {
+                    return Ok(());
+                }
+
+                let key = cache::details_key(&release_id);
+
+                let details = match result {
+                    Ok(info) => {
+                        self.cache.put(&key, &info)?;
+                        self.update_genres(&release_id, &info)?;
+                        Details::Loaded { info, stale: false }
+                    }
+                    Err(e) => match self.cache.get::<ReleaseDetails>(&key)? {
+                        Some(cached) => Details::Loaded {
+                            info: cached.value,
+                            stale: true,
+                        },
+                        None => {
+                            self.status = Some(format!("Couldn't fetch the details: {e}"));
+                            Details::Unavailable
+                        }
+                    },
+                };
+
+                self.details.insert(release_id, details);
+            }
+            Message::ReleasesPage { artist_id, result } => {
+                // The discography might have been closed or replaced while
+                // the page was being fetched.
+                let Some(discography) = &mut self.releases else {
+                    return Ok(());
+                };
+
+                let Some(pending) = &mut discography.pending else {
+                    return Ok(());
+                };
+
+                if discography.artist_id != artist_id {
+                    return Ok(());
+                }
+
+                match result {
+                    Ok(page) => {
+                        pending.count = page.count;
+                        self.add_releases(page.releases)?;
+
+                        if let Some(pending) =
+                            self.releases.as_ref().and_then(|d| d.pending.as_ref())
+                        {
+                            self.spawn_releases_page(artist_id, pending.fetched);
+                        }
+                    }
+                    Err(_) if pending.fetched == 0 => {
+                        let cached = self
+                            .cache
+                            .get::<Vec<Release>>(&cache::releases_key(&artist_id))?;
+
+                        match cached {
+                            Some(cached) => self.show_releases(cached.value, true)?,
+                            None => self.releases = None,
+                        }
+                    }
+                    Err(_) => discography.pending = None,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_releases(&mut self, new_releases: Vec<Release>) -> Result<()> {
+        let Some(discography) = &mut self.releases else {
+            return Ok(());
+        };
+
+        let Some(pending) = &mut discography.pending else {
+            return Ok(());
+        };
+
+        pending.fetched += new_releases.len();
+
+        let complete = new_releases.is_empty() || pending.fetched >= pending.count;
+
+        self.merge_releases(new_releases)?;
+
+        if complete && let Some(discography) = &mut self.releases {
+            discography.pending = None;
+
+            let releases = discography.releases().collect::<Vec<&Release>>();
+
+            self.cache
+                .put(&cache::releases_key(&discography.artist_id), &releases)?;
+        }
+
+        Ok(())
+    }
+
+    fn merge_releases(&mut self, mut new_releases: Vec<Release>) -> Result<()> {
+        let Some(discography) = &mut self.releases else {
+            return Ok(());
+        };
+
+        let ratings = database::get_ratings(&self.conn, &discography.artist_id)?;
+
+        for rating in ratings {
+            for release in &mut new_releases {
+                if release.id == rating.0 {
+                    release.rating = Some(rating.1);
+                }
+            }
+        }
+
+        for (release_id, average) in database::get_track_averages(&self.conn)? {
+            for release in &mut new_releases {
+                if release.id == release_id {
+                    release.track_average = Some(average);
+                }
+            }
+        }
+
+        for release in &mut new_releases {
+            release.in_backlog = self
+                .backlog
+                .iter_all()
+                .any(|item| item.release_id == release.id);
+        }
+
+        discography.arrange(new_releases);
+
+        Ok(())
+    }
+
+    /// Reloads the rated list and the ratings of the open discography after a
+    /// rating was changed in either of them.
+    fn refresh_ratings(&mut self) -> Result<()> {
+        let selected_id = self
+            .rated_list
+            .get_selected()
+            .map(|item| item.release_id.clone());
+
+        let mut rated = database::get_every_rating(&self.conn)?;
+        self.rated_total = rated.len();
+        self.history_release = None;
+
+        // The stats follow the filter, so that they can be narrowed down to a
+        // genre or a decade.
+        rated.retain(|item| self.rating_filter.matches(item));
+        self.stats = Stats::compute(&rated, stats::DEFAULT_MIN_RATINGS);
+
+        self.rating_sort.sort(&mut rated);
+        self.rated_list.set_items(rated);
+
+        let selected = self
+            .rated_list
+            .items
+            .iter()
+            .position(|item| Some(&item.release_id) == selected_id.as_ref())
+            .or((!self.rated_list.items.is_empty()).then_some(0));
+        self.rated_list.select(selected);
+
+        self.refresh_backlog()?;
+
+        if let Some(discography) = &mut self.releases {
+            let ratings = database::get_ratings(&self.conn, &discography.artist_id)?;
+            let averages = database::get_track_averages(&self.conn)?;
+
+            for release in discography.releases_mut() {
+                release.rating = ratings
+                    .iter()
+                    .find(|rating| rating.0 == release.id)
+                    .map(|rating| rating.1);
+                release.track_average = averages
+                    .iter()
+                    .find(|average| average.0 == release.id)
+                    .map(|average| average.1);
+                release.in_backlog = self
+                    .backlog
+                    .iter_all()
+                    .any(|item| item.release_id == release.id);
+            }
+
+            if discography.ranked {
+                discography.arrange(Vec::new());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reloads the backlog in its current order, keeping the selection.
+    fn refresh_backlog(&mut self) -> Result<()> {
+        let selected_id = self
+            .backlog
+            .get_selected()
+            .map(|item| item.release_id.clone());
+
+        let mut backlog = database::get_backlog(&self.conn)?;
+        self.backlog_sort.sort(&mut backlog);
+        self.backlog.set_items(backlog);
+
+        let selected = self
+            .backlog
+            .items
+            .iter()
+            .position(|item| Some(&item.release_id) == selected_id.as_ref())
+            .or((!self.backlog.items.is_empty()).then_some(0));
+        self.backlog.select(selected);
+
+        Ok(())
+    }
+
+    /// Changes the order of the rated list or the backlog, or ranks the
+    /// releases of the discography, whichever is shown.
+    pub fn cycle_sort(&mut self) -> Result<()> {
+        if self.currently_rating || self.tracklist().is_some() {
+            return Ok(());
+        }
+
+        if let Some(discography) = self.discography_mut() {
+            discography.ranked = !discography.ranked;
+            discography.arrange(Vec::new());
+            return Ok(());
+        }
+
+        match self.tab {
+            Tab::Ratings => {
+                self.rating_sort.next_field();
+                self.refresh_ratings()
+            }
+            Tab::Backlog => {
+                self.backlog_sort = self.backlog_sort.next();
+                self.refresh_backlog()
+            }
+            Tab::Search | Tab::Stats => Ok(()),
+        }
+    }
+
+    pub fn toggle_sort_direction(&mut self) -> Result<()> {
+        if self.tab == Tab::Ratings && self.discography().is_none() && !self.currently_rating {
+            self.rating_sort.toggle_direction();
+            self.refresh_ratings()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn open_filter_prompt(&mut self) {
+        if self.tab == Tab::Ratings && self.discography().is_none() && !self.currently_rating {
+            self.filter_prompt = Some(self.rating_filter.to_string());
+        }
+    }
+
+    pub fn close_filter_prompt(&mut self) {
+        self.filter_prompt = None;
+    }
+
+    /// The list that keys move around in.
+    fn focused_list(&mut self) -> Option<&mut dyn FuzzyFilter> {
+        if self.tracklist().is_some() {
+            return self
+                .tracklist_mut()
+                .map(|tracklist| &mut tracklist.list as &mut dyn FuzzyFilter);
+        }
+
+        if self.discography().is_some() {
+            return self
+                .discography_mut()
+                .map(|discography| &mut discography.list as &mut dyn FuzzyFilter);
+        }
+
+        match self.tab {
+            Tab::Search => self
+                .search_results
+                .as_mut()
+                .map(|results| results as &mut dyn FuzzyFilter),
+            Tab::Ratings => Some(&mut self.rated_list),
+            Tab::Backlog => Some(&mut self.backlog),
+            Tab::Stats => None,
+        }
+    }
+
+    pub fn open_find_prompt(&mut self) {
+        if self.currently_rating {
+            return;
+        }
+
+        if let Some(list) = self.focused_list() {
+            let query = list.query().to_string();
+            self.find_prompt = Some(query);
+        }
+    }
+
+    /// Narrows the focused list down to the query typed so far.
+    pub fn update_find_query(&mut self) {
+        let Some(query) = self.find_prompt.clone() else {
+            return;
+        };
+
+        if let Some(list) = self.focused_list() {
+            list.set_query(&query);
+        }
+    }
+
+    /// Stops typing, leaving the list narrowed down.
+    pub fn close_find_prompt(&mut self) {
+        self.find_prompt = None;
+    }
+
+    /// Stops typing and shows every item of the list again.
+    pub fn cancel_find(&mut self) {
+        self.find_prompt = None;
+
+        if let Some(list) = self.focused_list() {
+            list.set_query("");
+        }
+    }
+
+    pub fn submit_filter(&mut self) -> Result<()> {
+        let Some(query) = self.filter_prompt.take() else {
+            return Ok(());
+        };
+
+        match Filter::parse(&query) {
+            Ok(filter) => {
+                self.rating_filter = filter;
+                self.refresh_ratings()?;
+            }
+            Err(e) => {
+                self.status = Some(e);
+                self.filter_prompt = Some(query);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the selected release of a discography to the backlog, or removes
+    /// it if it is already there.
+    pub fn toggle_backlog(&mut self) -> Result<()> {
+        if self.currently_rating {
+            return Ok(());
+        }
+
+        if let Some(release) = self.get_selected_release() {
+            if release.rating.is_some() {
+                self.status = Some("Rated releases can't be added to the backlog".to_string());
+                return Ok(());
+            }
+
+            if !database::remove_from_backlog(&self.conn, &release.id)? {
+                let discography = self.discography().unwrap();
+
+                database::add_to_backlog(
+                    &self.conn,
+                    &discography.artist_id,
+                    &discography.artist_name,
+                    release,
+                )?;
+            }
+        } else if self.tab == Tab::Backlog && self.discography().is_none() {
+            if let Some(item) = self.backlog.get_selected() {
+                database::remove_from_backlog(&self.conn, &item.release_id)?;
+            }
+        } else {
+            return Ok(());
+        }
+
+        self.refresh_ratings()
+    }
+
+    pub fn start_rating(&mut self) {
+        let Some(item) = self.get_mut_selected_rateable() else {
+            return;
+        };
+
+        let previous_rating = *item.rating();
+
+        if previous_rating.is_none() {
+            item.set_rating(1);
+        }
+
+        self.previous_rating = previous_rating;
+        self.currently_rating = true;
+    }
+
+    pub fn set_rating(&mut self, rating: u8) {
+        if !self.currently_rating {
+            return;
+        }
+
+        if let Some(item) = self.get_mut_selected_rateable() {
+            item.set_rating(rating);
+        }
+    }
+
+    pub fn confirm_rating(&mut self) -> Result<()> {
+        if !self.currently_rating {
+            return Ok(());
+        }
+
+        if let Some(tracklist) = self.tracklist() {
+            // Tracks are stored with their position on the release, which
+            // differs from their index while the tracklist is filtered.
+            if let Some(idx) = tracklist.list.selected() {
+                database::set_track_rating(
+                    &self.conn,
+                    &tracklist.release_id,
+                    tracklist.list.position(idx) + 1,
+                    &tracklist.list.items[idx],
+                )?;
+            }
+        } else if let Some(release) = self.get_selected_release() {
+            let discography = self.discography().unwrap();
+
+            database::add_artist(&self.conn, &discography.artist_id, &discography.artist_name)?;
+            database::add_release(&self.conn, &discography.artist_id, release)?;
+        } else if self.discography().is_none() {
+            match self.tab {
+                Tab::Ratings => {
+                    if let Some(item) = self.rated_list.get_selected() {
+                        database::add_release(&self.conn, &item.artist_id, &Release::from(item))?;
+                    }
+                }
+                Tab::Backlog => {
+                    if let Some(item) = self.backlog.get_selected() {
+                        database::add_artist(&self.conn, &item.artist_id, &item.artist_name)?;
+                        database::add_release(&self.conn, &item.artist_id, &Release::from(item))?;
+                    }
+                }
+                Tab::Search | Tab::Stats => (),
+            }
+        }
+
+        self.currently_rating = false;
+        self.refresh_ratings()
+    }
+
+    pub fn abort_rating(&mut self) {
+        if !self.currently_rating {
+            return;
+        }
+
+        self.currently_rating = false;
+        let prev = self.previous_rating;
+
+        if let Some(item) = self.get_mut_selected_rateable() {
+            *item.rating() = prev;
+        }
+    }
+
+    /// The release that is selected in the discography or the rated list.
+    fn get_selected_release_id(&self) -> Option<&str> {
+        if let Some(release) = self.get_selected_release() {
+            return Some(&release.id);
+        }
+
+        match self.tab {
+            Tab::Ratings if self.discography().is_none() => self
+                .rated_list
+                .get_selected()
+                .map(|item| item.release_id.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn toggle_history(&mut self) {
+        self.show_history = !self.show_history;
+    }
+
+    /// Stores freshly fetched genres and community tags of a rated release.
+    fn update_genres(&mut self, release_id: &str, details: &ReleaseDetails) -> Result<()> {
+        let Some(item) = database::get_rating(&self.conn, release_id)? else {
+            return Ok(());
+        };
+
+        database::update_release_genres(&self.conn, release_id, &details.genres, &details.tags)?;
+
+        // Empty lists don't replace anything, so there's nothing to refresh.
+        if (details.genres.is_empty() || item.genres == details.genres)
+            && (details.tags.is_empty() || item.community_tags == details.tags)
+        {
+            return Ok(());
+        }
+
+        self.refresh_ratings()
+    }
+
+    pub fn toggle_details(&mut self) {
+        self.show_details = !self.show_details;
+    }
+
+    /// The release that is selected in the discography, the rated list or the
+    /// backlog.
+    pub fn selected_release(&self) -> Option<Release> {
+        if let Some(release) = self.get_selected_release() {
+            return Some(release.clone());
+        }
+
+        match self.tab {
+            _ if self.discography().is_some() => None,
+            Tab::Ratings => self.rated_list.get_selected().map(Release::from),
+            Tab::Backlog => self.backlog.get_selected().map(Release::from),
+            _ => None,
+        }
+    }
+
+    /// Starts fetching the details of the selected release while the detail
+    /// panel is shown, unless they are loaded or fresh in the cache already.
+    /// Details that are still being fetched are dropped once another release
+    /// is selected.
+    pub fn load_details(&mut self) -> Result<()> {
+        if !self.show_details {
+            return Ok(());
+        }
+
+        let Some(release_id) = self.selected_release().map(|release| release.id) else {
+            return Ok(());
+        };
+
+        if self.details.contains_key(&release_id) {
+            return Ok(());
+        }
+
+        match self
+            .cache
+            .get::<ReleaseDetails>(&cache::details_key(&release_id))?
+        {
+            Some(cached) if !cached.stale || self.cache.offline => {
+                self.details.insert(
+                    release_id,
+                    Details::Loaded {
+                        info: cached.value,
+                        stale: cached.stale,
+                    },
+                );
+                return Ok(());
+            }
+            _ if self.cache.offline => {
+                self.details.insert(release_id, Details::Unavailable);
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        if let Some((loading_id, task)) = self.details_task.take()
+            && !task.is_finished()
+        {
+            task.abort();
+            self.details.remove(&loading_id);
+        }
+
+        self.details.insert(release_id.clone(), Details::Loading);
+
+        let tx = self.tx.clone();
+        let task_release_id = release_id.clone();
+
+        let task = tokio::spawn(async move {
+            let result = fetch_release_details(&task_release_id).await;
+            let _ = tx.send(Message::Details {
+                release_id: task_release_id,
+                result,
+            });
+        });
+        self.details_task = Some((release_id, task));
+
+        Ok(())
+    }
+
+    /// Starts loading the cover of the selected release of the discography,
+    /// unless it's loaded already. A cover that is still loading is dropped
+    /// once another release is selected.
+    pub fn load_cover(&mut self) {
+        let Some(release_id) = self
+            .discography()
+            .filter(|discography| discography.tracklist.is_none())
+            .and_then(|_| self.get_selected_release())
+            .map(|release| release.id.clone())
+        else {
+            return;
+        };
+
+        if self.covers.contains_key(&release_id) {
+            return;
+        }
+
+        if let Some((loading_id, task)) = self.cover_task.take()
+            && !task.is_finished()
+        {
+            task.abort();
+            self.covers.remove(&loading_id);
+        }
+
+        self.covers.insert(release_id.clone(), Cover::Loading);
+
+        let fetcher = self.cover_fetcher.clone();
+        let tx = self.tx.clone();
+        let task_release_id = release_id.clone();
+
+        let task = tokio::spawn(async move {
+            let result = fetcher.load(&task_release_id).await;
+            let _ = tx.send(Message::Cover {
+                release_id: task_release_id,
+                result,
+            });
+        });
+        self.cover_task = Some((release_id, task));
+    }
+
+    /// The cover to draw with terminal graphics, and where to draw it.
+    pub fn placed_cover(&self) -> Option<(&CoverPlacement, &RgbaImage)> {
+        let placement = self.cover_placement.as_ref()?;
+
+        match self.covers.get(&placement.release_id) {
+            Some(Cover::Image(image)) => Some((placement, image)),
+            _ => None,
+        }
+    }
+
+    /// Loads the history, the review and the tags of the selected release for
+    /// the side panels.
+    pub fn load_history(&mut self) -> Result<()> {
+        let release_id = self
+            .selected_release()
+            .filter(|_| self.show_history || self.show_details)
+            .map(|release| release.id);
+
+        if release_id == self.history_release {
+            return Ok(());
+        }
+
+        (self.history, self.review, self.tags, self.artist_genres) = match &release_id {
+            Some(release_id) => (
+                database::get_rating_events(&self.conn, Some(release_id), None)?,
+                database::get_review(&self.conn, release_id)?,
+                database::get_tags(&self.conn, release_id)?,
+                database::get_artist_genres(&self.conn, release_id)?,
+            ),
+            None => (Vec::new(), None, Vec::new(), Vec::new()),
+        };
+        self.history_release = release_id;
+
+        Ok(())
+    }
+
+    /// Reverts the last change to the rating of the selected release. The
+    /// revert is recorded as a change as well, so undoing twice redoes it.
+    pub fn undo_rating(&mut self) -> Result<()> {
+        if self.currently_rating {
+            return Ok(());
+        }
+
+        let Some(release_id) = self.get_selected_release_id().map(str::to_string) else {
+            return Ok(());
+        };
+
+        let Some(event) = database::get_last_rating_event(&self.conn, &release_id)? else {
+            self.status = Some("Nothing to undo".to_string());
+            return Ok(());
+        };
+
+        let Some(old_rating) = event.old_rating else {
+            database::delete_release(&self.conn, &release_id)?;
+            return self.refresh_ratings();
+        };
+
+        let (artist_id, artist_name, mut release) =
+            if let Some(release) = self.get_selected_release() {
+                let discography = self.discography().unwrap();
+                (
+                    discography.artist_id.clone(),
+                    discography.artist_name.clone(),
+                    release.clone(),
+                )
+            } else if let Some(item) = self.rated_list.get_selected() {
+                (
+                    item.artist_id.clone(),
+                    item.artist_name.clone(),
+                    Release::from(item),
+                )
+            } else {
+                return Ok(());
+            };
+
+        release.set_rating(old_rating);
+
+        database::add_artist(&self.conn, &artist_id, &artist_name)?;
+        database::add_release(&self.conn, &artist_id, &release)?;
+
+        self.refresh_ratings()
+    }
+
+    /// The identifier and a description of the selected release, if it is
+    /// rated.
+    fn describe_selected_rating(&self) -> Option<(String, String)> {
+        if let Some(release) = self.get_selected_release() {
+            let artist_name = &self.discography().unwrap().artist_name;
+
+            return release
+                .rating
+                .map(|_| (release.id.clone(), format!("{artist_name} - {release}")));
+        }
+
+        match self.tab {
+            Tab::Ratings if self.discography().is_none() => self
+                .rated_list
+                .get_selected()
+                .map(|item| (item.release_id.clone(), item.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn open_review_editor(&mut self) -> Result<()> {
+        if self.currently_rating {
+            return Ok(());
+        }
+
+        let Some((release_id, description)) = self.describe_selected_rating() else {
+            self.status = Some("Only rated releases can be reviewed".to_string());
+            return Ok(());
+        };
+
+        let text = database::get_review(&self.conn, &release_id)?.unwrap_or_default();
+
+        self.review_editor = Some(ReviewEditor {
+            release_id,
+            description,
+            text,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_review_editor(&mut self) {
+        self.review_editor = None;
+    }
+
+    pub fn save_review(&mut self) -> Result<()> {
+        let Some(editor) = self.review_editor.take() else {
+            return Ok(());
+        };
+
+        database::set_review(&self.conn, &editor.release_id, editor.text.trim())?;
+
+        self.refresh_ratings()
+    }
+
+    /// Opens the tag editor for the selected release, which has to be rated
+    /// or in the backlog.
+    pub fn open_tag_editor(&mut self) -> Result<()> {
+        if self.currently_rating {
+            return Ok(());
+        }
+
+        let Some(release) = self
+            .selected_release()
+            .filter(|release| release.rating.is_some() || release.in_backlog)
+        else {
+            self.status = Some("Only rated or backlogged releases can be tagged".to_string());
+            return Ok(());
+        };
+
+        let tags = database::get_tags(&self.conn, &release.id)?;
+        let mut text = join_tags(&tags);
+
+        if !text.is_empty() {
+            text.push_str(", ");
+        }
+
+        self.tag_editor = Some(TagEditor {
+            description: release.to_string(),
+            release_id: release.id,
+            text,
+            known: database::get_tag_names(&self.conn)?,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_tag_editor(&mut self) {
+        self.tag_editor = None;
+    }
+
+    pub fn save_tags(&mut self) -> Result<()> {
+        let Some(editor) = self.tag_editor.take() else {
+            return Ok(());
+        };
+
+        database::set_tags(&self.conn, &editor.release_id, &parse_tags(&editor.text))?;
+
+        self.refresh_ratings()
+    }
+
+    /// Asks for confirmation before removing the rating of the selected
+    /// release, or of the selected track while a tracklist is open.
+    pub fn start_unrate(&mut self) {
+        if self.currently_rating {
+            return;
+        }
+
+        if let Some(tracklist) = self.tracklist() {
+            self.pending_unrate = tracklist
+                .list
+                .get_selected()
+                .filter(|track| track.rating.is_some())
+                .map(|track| PendingUnrate {
+                    release_id: tracklist.release_id.clone(),
+                    recording_id: Some(track.id.clone()),
+                    description: track.to_string(),
+                });
+            return;
+        }
+
+        self.pending_unrate = self
+            .describe_selected_rating()
+            .map(|(release_id, description)| PendingUnrate {
+                release_id,
+                recording_id: None,
+                description,
+            });
+    }
+
+    pub fn cancel_unrate(&mut self) {
+        self.pending_unrate = None;
+    }
+
+    pub fn confirm_unrate(&mut self) -> Result<()> {
+        let Some(pending) = self.pending_unrate.take() else {
+            return Ok(());
+        };
+
+        if let Some(recording_id) = &pending.recording_id {
+            database::delete_track_rating(&self.conn, &pending.release_id, recording_id)?;
+
+            if let Some(tracklist) = self.tracklist_mut() {
+                for track in tracklist.list.iter_all_mut() {
+                    if track.id == *recording_id {
+                        track.rating = None;
+                    }
+                }
+            }
+
+            return self.refresh_ratings();
+        }
+
+        database::delete_release(&self.conn, &pending.release_id)?;
+        self.history_release = None;
+
+        // The rating that takes the place of a selected one is selected
+        // instead of the first one.
+        let selected = self
+            .rated_list
+            .get_selected()
+            .filter(|item| item.release_id == pending.release_id)
+            .and(self.rated_list.selected());
+
+        // Reloading drops the rating from the items a fuzzy filter hides as
+        // well, and updates the total, the stats and a ranked discography.
+        self.refresh_ratings()?;
+
+        if let Some(idx) = selected
+            && !self.rated_list.items.is_empty()
+        {
+            self.rated_list
+                .select(Some(idx.min(self.rated_list.items.len() - 1)));
+        }
+
+        Ok(())
+    }
+}
+
+/// Groups releases by their type, and orders each group chronologically.
+pub fn sort_releases(releases: &mut [Release]) {
+    releases.sort_by(|a, b| {
+        a.group_type
+            .sort_key()
+            .cmp(&b.group_type.sort_key())
+            .then_with(|| {
+                a.first_release_date
+                    .is_none()
+                    .cmp(&b.first_release_date.is_none())
+            })
+            .then_with(|| a.first_release_date.cmp(&b.first_release_date))
+    });
+}
+
+/// Orders the releases from the highest rated, with the unrated ones last.
+/// Releases with the same rating are ordered by release date.
+pub fn rank_releases(releases: &mut [Release]) {
+    releases.sort_by(|a, b| {
+        b.rating
+            .cmp(&a.rating)
+            .then_with(|| {
+                a.first_release_date
+                    .is_none()
+                    .cmp(&b.first_release_date.is_none())
+            })
+            .then_with(|| a.first_release_date.cmp(&b.first_release_date))
+    });
+}
+
+pub fn insert_headers(releases: Vec<Release>) -> Vec<ListItemType> {
+    let mut releases = releases.into_iter();
+    let mut result = if let Some(release) = releases.next() {
+        vec![
+            ListItemType::ReleaseType(release.group_type.clone()),
+            ListItemType::Release(release),
+        ]
+    } else {
+        return Vec::new();
+    };
+
+    for release in releases {
+        if matches!(result.last(), Some(ListItemType::Release(last_item)) if release.group_type != last_item.group_type)
+        {
+            result.push(ListItemType::ReleaseType(release.group_type.clone()));
+        }
+
+        result.push(ListItemType::Release(release));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> TagEditor {
+        TagEditor {
+            release_id: String::new(),
+            description: String::new(),
+            text: text.to_string(),
+            known: vec!["Road trip".to_string(), "revisit".to_string()],
+        }
+    }
+
+    #[test]
+    fn suggests_known_tags() {
+        assert_eq!(editor("ro").completion(), Some("ad trip"));
+        assert_eq!(editor("revisit, RO").completion(), Some("ad trip"));
+        assert_eq!(editor("").completion(), None);
+        assert_eq!(editor("road trip, ").completion(), None);
+        assert_eq!(editor("jazz").completion(), None);
+    }
+
+    #[test]
+    fn skips_tags_that_are_entered_already() {
+        assert_eq!(editor("road trip, r").completion(), Some("evisit"));
+        assert_eq!(editor("road trip, revisit").completion(), None);
+    }
+
+    #[test]
+    fn completes_in_the_known_case() {
+        let mut editor = editor("revisit,  ro");
+        editor.complete();
+
+        assert_eq!(editor.text, "revisit,  Road trip, ");
+    }
 }
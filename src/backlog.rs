@@ -0,0 +1,169 @@
+use crate::{
+    app::ReleaseType,
+    musicbrainz::Release,
+    rating::Rate,
+    ui::Searchable,
+    utils::{format_timestamp, join_tags},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// A release that is waiting to be listened to. Releases leave the backlog
+/// once they are rated.
+pub struct BacklogItem {
+    pub artist_id: String,
+    pub artist_name: String,
+    pub release_id: String,
+    pub title: String,
+    pub year: Option<i32>,
+    pub group_type: ReleaseType,
+    pub first_release_date: Option<String>,
+    /// Unix timestamp of when the release was added to the backlog.
+    pub added_at: Option<i64>,
+    /// The rating being given from the backlog, before it is confirmed.
+    pub rating: Option<u8>,
+    /// Our own tags.
+    pub tags: Vec<String>,
+}
+
+impl BacklogItem {
+    pub fn date_added(&self) -> String {
+        self.added_at
+            .map(format_timestamp)
+            .unwrap_or_else(|| "unknown date".to_string())
+    }
+}
+
+impl Rate for BacklogItem {
+    fn rating(&mut self) -> &mut Option<u8> {
+        &mut self.rating
+    }
+}
+
+impl TryFrom<&rusqlite::Row<'_>> for BacklogItem {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row) -> Result<Self, Self::Error> {
+        Ok(BacklogItem {
+            artist_id: row.get(0)?,
+            artist_name: row.get(1)?,
+            release_id: row.get(2)?,
+            title: row.get(3)?,
+            year: row.get(4)?,
+            group_type: ReleaseType::from_columns(row.get(5)?, row.get(6)?),
+            first_release_date: row.get(7)?,
+            added_at: row.get(8)?,
+            rating: None,
+            tags: Vec::new(),
+        })
+    }
+}
+
+impl From<&BacklogItem> for Release {
+    fn from(value: &BacklogItem) -> Self {
+        Release {
+            id: value.release_id.clone(),
+            title: value.title.clone(),
+            year: value.year.unwrap_or_default(),
+            first_release_date: value.first_release_date.clone(),
+            group_type: value.group_type.clone(),
+            community: None,
+            genres: Vec::new(),
+            community_tags: Vec::new(),
+            rating: value.rating,
+            track_average: None,
+            in_backlog: true,
+        }
+    }
+}
+
+impl Display for BacklogItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{} - ({}) {}", self.artist_name, year, self.title),
+            None => write!(f, "{} - {}", self.artist_name, self.title),
+        }
+    }
+}
+
+impl Searchable for BacklogItem {
+    fn search_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum BacklogSort {
+    #[default]
+    Added,
+    Artist,
+    Year,
+    Title,
+}
+
+impl BacklogSort {
+    pub fn name(self) -> &'static str {
+        match self {
+            BacklogSort::Added => "date added",
+            BacklogSort::Artist => "artist",
+            BacklogSort::Year => "year",
+            BacklogSort::Title => "title",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            BacklogSort::Added => BacklogSort::Artist,
+            BacklogSort::Artist => BacklogSort::Year,
+            BacklogSort::Year => BacklogSort::Title,
+            BacklogSort::Title => BacklogSort::Added,
+        }
+    }
+
+    pub fn sort(self, items: &mut [BacklogItem]) {
+        match self {
+            BacklogSort::Added => items.sort_by_key(|item| item.added_at),
+            BacklogSort::Artist => items.sort_by_cached_key(|item| {
+                (
+                    item.artist_name.to_lowercase(),
+                    item.first_release_date.clone(),
+                )
+            }),
+            BacklogSort::Year => {
+                items.sort_by_key(|item| (item.year.is_none(), item.first_release_date.clone()))
+            }
+            BacklogSort::Title => items.sort_by_cached_key(|item| item.title.to_lowercase()),
+        }
+    }
+}
+
+/// A backlog entry as it is written to an export.
+#[derive(Serialize, Deserialize)]
+pub struct BacklogRecord {
+    pub artist_id: String,
+    pub artist_name: String,
+    pub release_id: String,
+    pub title: String,
+    pub year: Option<i32>,
+    #[serde(rename = "type")]
+    pub release_type: String,
+    pub added_at: Option<i64>,
+    /// Our tags, separated by commas.
+    #[serde(default)]
+    pub tags: Option<String>,
+}
+
+impl From<BacklogItem> for BacklogRecord {
+    fn from(value: BacklogItem) -> Self {
+        BacklogRecord {
+            artist_id: value.artist_id,
+            artist_name: value.artist_name,
+            release_id: value.release_id,
+            title: value.title,
+            year: value.year,
+            release_type: value.group_type.to_string(),
+            added_at: value.added_at,
+            tags: (!value.tags.is_empty()).then(|| join_tags(&value.tags)),
+        }
+    }
+}
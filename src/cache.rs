@@ -0,0 +1,168 @@
+use crate::utils::get_cache_path;
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Serialize, de::DeserializeOwned};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A response served from the cache. Entries older than the TTL are still
+/// returned so that they can be shown when offline, but are marked as stale.
+pub struct Cached<T> {
+    pub value: T,
+    pub stale: bool,
+}
+
+/// MusicBrainz responses stored next to the ratings database. Unlike the
+/// ratings, everything in here can be fetched again, so the cache database can
+/// be deleted at any time.
+pub struct Cache {
+    conn: Connection,
+    ttl: Duration,
+    pub offline: bool,
+}
+
+impl Cache {
+    pub fn open(ttl: Duration, offline: bool) -> Result<Self> {
+        Self::with_connection(Connection::open(get_cache_path()?)?, ttl, offline)
+    }
+
+    fn with_connection(conn: Connection, ttl: Duration, offline: bool) -> Result<Self> {
+        conn.execute(
+            "
+                CREATE TABLE IF NOT EXISTS responses (
+                    key TEXT PRIMARY KEY,
+                    value TEXT,
+                    fetched_at INTEGER
+                )
+            ",
+            [],
+        )?;
+
+        Ok(Self { conn, ttl, offline })
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<Cached<T>>> {
+        let entry = self
+            .conn
+            .query_row(
+                "
+                    SELECT value, fetched_at
+                    FROM responses
+                    WHERE key=?1
+                ",
+                params![key],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)),
+            )
+            .optional()?;
+
+        let Some((value, fetched_at)) = entry else {
+            return Ok(None);
+        };
+
+        // Entries that no longer deserialize were written by an older version
+        // and are treated as missing.
+        let Ok(value) = serde_json::from_str(&value) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Cached {
+            value,
+            stale: now().saturating_sub(fetched_at) > self.ttl.as_secs(),
+        }))
+    }
+
+    pub fn put<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        self.conn.execute(
+            "
+                INSERT OR REPLACE INTO responses (key, value, fetched_at)
+                VALUES(?1, ?2, ?3)
+            ",
+            params![key, serde_json::to_string(value)?, now()],
+        )?;
+
+        Ok(())
+    }
+}
+
+pub fn search_key(query: &str) -> String {
+    format!("search:{}", query.trim().to_lowercase())
+}
+
+pub fn releases_key(artist_id: &str) -> String {
+    format!("releases:{artist_id}")
+}
+
+pub fn tracks_key(release_id: &str) -> String {
+    format!("tracks:{release_id}")
+}
+
+pub fn details_key(release_id: &str) -> String {
+    format!("details:{release_id}")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASE_ID: &str = "22222222-2222-2222-2222-222222222222";
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn open() -> Cache {
+        Cache::with_connection(Connection::open_in_memory().unwrap(), HOUR, false).unwrap()
+    }
+
+    #[test]
+    fn reads_back_fresh_entries() {
+        let cache = open();
+        let key = details_key(RELEASE_ID);
+        cache.put(&key, &vec!["rock".to_string()]).unwrap();
+
+        let cached = cache.get::<Vec<String>>(&key).unwrap().unwrap();
+
+        assert_eq!(key, format!("details:{RELEASE_ID}"));
+        assert_eq!(cached.value, ["rock"]);
+        assert!(!cached.stale);
+        assert!(
+            cache
+                .get::<Vec<String>>(&tracks_key(RELEASE_ID))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    /// Stale entries are still served, so that they can be shown offline.
+    #[test]
+    fn keeps_entries_older_than_the_ttl_as_stale() {
+        let cache = open();
+        let key = details_key(RELEASE_ID);
+        cache.put(&key, &vec!["rock".to_string()]).unwrap();
+
+        cache
+            .conn
+            .execute(
+                "UPDATE responses SET fetched_at = fetched_at - ?1",
+                params![HOUR.as_secs() + 1],
+            )
+            .unwrap();
+
+        let cached = cache.get::<Vec<String>>(&key).unwrap().unwrap();
+
+        assert_eq!(cached.value, ["rock"]);
+        assert!(cached.stale);
+    }
+
+    #[test]
+    fn treats_entries_of_another_shape_as_missing() {
+        let cache = open();
+        let key = details_key(RELEASE_ID);
+        cache.put(&key, &"not a list").unwrap();
+
+        assert!(cache.get::<Vec<String>>(&key).unwrap().is_none());
+    }
+}
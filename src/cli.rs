@@ -1,4 +1,6 @@
-use clap::{Arg, ArgAction, ArgMatches, Command};
+use crate::rating::parse_decade;
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
+use std::path::PathBuf;
 
 pub fn get_matches() -> ArgMatches {
     Command::new(env!("CARGO_PKG_NAME"))
@@ -9,6 +11,251 @@ pub fn get_matches() -> ArgMatches {
                 .help("List rated albums")
                 .action(ArgAction::SetTrue),
         )
-        .arg(Arg::new("artist").value_name("ARTIST"))
+        .arg(
+            Arg::new("backfill")
+                .long("backfill")
+                .help("Fetch missing release metadata of rated albums from MusicBrainz")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .help("Only use cached MusicBrainz data")
+                .global(true)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("cache_ttl")
+                .long("cache-ttl")
+                .value_name("HOURS")
+                .help("How long cached MusicBrainz data is considered fresh")
+                .global(true)
+                .default_value("168")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("graphics")
+                .long("graphics")
+                .value_name("PROTOCOL")
+                .help("How cover art is drawn")
+                .default_value("auto")
+                .value_parser(["auto", "kitty", "sixel", "halfblocks"]),
+        )
+        .arg(
+            Arg::new("covers_from")
+                .long("covers-from")
+                .value_name("DIR")
+                .help("Read cover art from <release group MBID>.jpg files in DIR instead of the Cover Art Archive")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("artist")
+                .short('a')
+                .long("artist")
+                .value_name("ARTIST")
+                .help("Search for ARTIST on startup"),
+        )
+        .subcommand(
+            Command::new("rate")
+                .about("Rate a release group")
+                .arg(
+                    Arg::new("release")
+                        .value_name("RELEASE_GROUP_MBID")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("rating")
+                        .value_name("RATING")
+                        .help("A rating from 1 to 10")
+                        .required(true)
+                        .value_parser(value_parser!(u8).range(1..=10)),
+                ),
+        )
+        .subcommand(
+            Command::new("unrate")
+                .about("Remove the rating of a release group")
+                .arg(
+                    Arg::new("release")
+                        .value_name("RELEASE_GROUP_MBID")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("Print rated albums")
+                .arg(
+                    Arg::new("artist")
+                        .long("artist")
+                        .value_name("NAME")
+                        .help("Only list artists whose name contains NAME"),
+                )
+                .arg(
+                    Arg::new("min")
+                        .long("min")
+                        .value_name("RATING")
+                        .help("Only list ratings of at least RATING")
+                        .value_parser(value_parser!(u8).range(1..=10)),
+                )
+                .arg(
+                    Arg::new("max")
+                        .long("max")
+                        .value_name("RATING")
+                        .help("Only list ratings of at most RATING")
+                        .value_parser(value_parser!(u8).range(1..=10)),
+                )
+                .arg(
+                    Arg::new("decade")
+                        .long("decade")
+                        .value_name("DECADE")
+                        .help("Only list releases from the given decade, e.g. 1990")
+                        .value_parser(parse_decade),
+                )
+                .arg(
+                    Arg::new("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .help("Only list releases of the given type, e.g. Album or Live"),
+                )
+                .arg(
+                    Arg::new("genre")
+                        .long("genre")
+                        .value_name("GENRE")
+                        .help("Only list releases of the given genre, e.g. \"hip hop\""),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .value_name("TAG")
+                        .help("Only list releases with one of our tags"),
+                )
+                .arg(
+                    Arg::new("disagreements")
+                        .long("disagreements")
+                        .help("List the releases rated furthest from the MusicBrainz community rating first")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Print the changes to ratings")
+                .arg(
+                    Arg::new("release")
+                        .value_name("RELEASE_GROUP_MBID")
+                        .help("Only print the changes to this release group"),
+                )
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_name("DATE")
+                        .help("Only print changes made on or after DATE, given as YYYY-MM-DD"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Print statistics of the ratings")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the statistics as JSON")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("min_ratings")
+                        .long("min-ratings")
+                        .value_name("COUNT")
+                        .help("Only rank artists with at least COUNT ratings [default: 3]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("genre")
+                        .long("genre")
+                        .value_name("GENRE")
+                        .help("Only count releases of the given genre"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export every rating")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .value_name("FORMAT")
+                        .default_value("json")
+                        .value_parser(["json", "csv", "tsv"]),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Write to FILE instead of the standard output")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("backlog")
+                        .long("backlog")
+                        .help("Export the backlog instead of the ratings")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import ratings or the backlog from an export")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .value_name("FORMAT")
+                        .help("Format of the file, guessed from its extension by default")
+                        .value_parser(["json", "csv", "tsv"]),
+                )
+                .arg(
+                    Arg::new("on_conflict")
+                        .long("on-conflict")
+                        .value_name("POLICY")
+                        .help("How to resolve releases that are rated differently")
+                        .default_value("keep")
+                        .value_parser(["keep", "overwrite", "max", "newest"]),
+                )
+                .arg(
+                    Arg::new("dry_run")
+                        .long("dry-run")
+                        .help("Print what would change without changing anything")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("backlog")
+                        .long("backlog")
+                        .help("Import a backlog export instead of ratings")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("import-rym")
+                .about("Import ratings from a RateYourMusic CSV export")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Print the discography of an artist along with its ratings")
+                .arg(
+                    Arg::new("artist")
+                        .value_name("ARTIST")
+                        .help("Name or MBID of the artist")
+                        .required(true),
+                ),
+        )
         .get_matches()
 }
@@ -0,0 +1,451 @@
+use crate::{
+    app::{ListItemType, insert_headers, sort_releases},
+    cache::{self, Cache},
+    database,
+    export::{self, Format},
+    import::{self, Change, ConflictPolicy},
+    musicbrainz::{self, ArtistSearchResult, Release},
+    rating::{Filter, Rate, RatingSort, SortField},
+    rym::{self, Resolution, Review},
+    stats::{self, Stats},
+    utils::{is_mbid, parse_date},
+};
+use anyhow::Result;
+use clap::ArgMatches;
+use rusqlite::Connection;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+/// Exit code for arguments that clap can't validate, such as malformed MBIDs.
+/// Matches the exit code clap uses for usage errors.
+const EXIT_INVALID_INPUT: u8 = 2;
+
+/// Exit code for a release or artist that couldn't be found.
+const EXIT_NOT_FOUND: u8 = 3;
+
+pub async fn run(name: &str, matches: &ArgMatches, cache: Cache) -> Result<ExitCode> {
+    let conn = database::open_db()?;
+
+    match name {
+        "rate" => rate(&conn, matches, &cache).await,
+        "unrate" => unrate(&conn, matches),
+        "list" => list(&conn, matches),
+        "show" => show(&conn, matches, &cache).await,
+        "history" => history(&conn, matches),
+        "stats" => stats(&conn, matches),
+        "export" => export(&conn, matches),
+        "import" => import(&conn, matches),
+        "import-rym" => import_rym(&conn, matches, &cache).await,
+        _ => unreachable!(),
+    }
+}
+
+fn get_mbid<'a>(matches: &'a ArgMatches, id: &str) -> Option<&'a str> {
+    let mbid = matches.get_one::<String>(id).unwrap();
+
+    if is_mbid(mbid) {
+        Some(mbid)
+    } else {
+        eprintln!("\"{mbid}\" is not a valid MusicBrainz identifier");
+        None
+    }
+}
+
+async fn rate(conn: &Connection, matches: &ArgMatches, cache: &Cache) -> Result<ExitCode> {
+    let Some(release_id) = get_mbid(matches, "release") else {
+        return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+    };
+    let rating = *matches.get_one::<u8>("rating").unwrap();
+
+    let (artist_id, artist_name, mut release) = match database::get_rating(conn, release_id)? {
+        Some(item) => {
+            let release = Release::from(&item);
+            (item.artist_id, item.artist_name, release)
+        }
+        None if cache.offline => {
+            eprintln!("{release_id} hasn't been rated before and can't be fetched offline");
+            return Ok(ExitCode::from(EXIT_NOT_FOUND));
+        }
+        None => match musicbrainz::fetch_release(release_id).await {
+            Ok((release, Some(artist))) => (artist.id, artist.name, release),
+            Ok((_, None)) => {
+                eprintln!("{release_id} isn't credited to any artist");
+                return Ok(ExitCode::from(EXIT_NOT_FOUND));
+            }
+            Err(e) => {
+                eprintln!("Couldn't find release group {release_id}: {e}");
+                return Ok(ExitCode::from(EXIT_NOT_FOUND));
+            }
+        },
+    };
+
+    release.set_rating(rating);
+
+    database::add_artist(conn, &artist_id, &artist_name)?;
+    database::add_release(conn, &artist_id, &release)?;
+
+    println!("{artist_name} - {release}: {rating}/10");
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn unrate(conn: &Connection, matches: &ArgMatches) -> Result<ExitCode> {
+    let Some(release_id) = get_mbid(matches, "release") else {
+        return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+    };
+
+    let Some(item) = database::get_rating(conn, release_id)? else {
+        eprintln!("{release_id} isn't rated");
+        return Ok(ExitCode::from(EXIT_NOT_FOUND));
+    };
+
+    database::delete_release(conn, release_id)?;
+
+    println!("Removed the rating of {item}");
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn list(conn: &Connection, matches: &ArgMatches) -> Result<ExitCode> {
+    let filter = Filter {
+        min_rating: matches.get_one::<u8>("min").copied(),
+        max_rating: matches.get_one::<u8>("max").copied(),
+        artist: matches.get_one::<String>("artist").cloned(),
+        decade: matches.get_one::<i32>("decade").copied(),
+        release_type: matches.get_one::<String>("type").cloned(),
+        genre: matches.get_one::<String>("genre").cloned(),
+        tag: matches.get_one::<String>("tag").cloned(),
+    };
+
+    let mut ratings = database::get_every_rating(conn)?;
+    ratings.retain(|item| filter.matches(item));
+
+    if matches.get_flag("disagreements") {
+        ratings.retain(|item| item.disagreement().is_some());
+        RatingSort {
+            field: SortField::Disagreement,
+            descending: true,
+        }
+        .sort(&mut ratings);
+    }
+
+    for item in ratings {
+        let difference = match (item.rating, item.community) {
+            (Some(rating), Some(community)) => format!("{:+.1}", community.difference(rating)),
+            _ => String::new(),
+        };
+
+        println!(
+            "{:>2}  {difference:>5}  {}  {}",
+            item.rating.unwrap_or_default(),
+            item,
+            item.group_type
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn history(conn: &Connection, matches: &ArgMatches) -> Result<ExitCode> {
+    let release_id = match matches.get_one::<String>("release") {
+        Some(_) => match get_mbid(matches, "release") {
+            Some(release_id) => Some(release_id),
+            None => return Ok(ExitCode::from(EXIT_INVALID_INPUT)),
+        },
+        None => None,
+    };
+
+    let since = match matches.get_one::<String>("since") {
+        Some(date) => match parse_date(date) {
+            Some(since) => Some(since),
+            None => {
+                eprintln!("\"{date}\" is not a date in the YYYY-MM-DD format");
+                return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+            }
+        },
+        None => None,
+    };
+
+    for event in database::get_rating_events(conn, release_id, since)? {
+        let release = match (&event.artist_name, &event.title) {
+            (Some(artist_name), Some(title)) => format!("{artist_name} - {title}"),
+            _ => event.release_id.clone(),
+        };
+
+        println!("{}  {:>7}  {release}", event.date(), event.change());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn stats(conn: &Connection, matches: &ArgMatches) -> Result<ExitCode> {
+    let min_ratings = matches
+        .get_one::<usize>("min_ratings")
+        .copied()
+        .unwrap_or(stats::DEFAULT_MIN_RATINGS);
+    let mut ratings = database::get_every_rating(conn)?;
+
+    if let Some(genre) = matches.get_one::<String>("genre") {
+        let filter = Filter {
+            genre: Some(genre.clone()),
+            ..Filter::default()
+        };
+        ratings.retain(|item| filter.matches(item));
+    }
+
+    let stats = Stats::compute(&ratings, min_ratings);
+
+    if matches.get_flag("json") {
+        serde_json::to_writer_pretty(io::stdout().lock(), &stats)?;
+        println!();
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    match stats.average {
+        Some(average) => println!("{} ratings, {average:.2} on average", stats.total),
+        None => println!("No ratings"),
+    }
+
+    println!("\nDistribution");
+    for (idx, count) in stats.distribution.iter().enumerate() {
+        println!("{:>4}  {count}", idx + 1);
+    }
+
+    println!("\nDecades");
+    for decade in &stats.decades {
+        println!(
+            "{:>5}s  {:>4}  {:.2}",
+            decade.decade, decade.count, decade.average
+        );
+    }
+
+    println!("\nTop artists with at least {min_ratings} ratings");
+    for artist in &stats.top_artists {
+        println!(
+            "{:.2}  {}  ({} ratings)",
+            artist.average, artist.artist_name, artist.count
+        );
+    }
+
+    println!("\nRelease types");
+    for release_type in &stats.release_types {
+        println!("{:>4}  {}", release_type.count, release_type.release_type);
+    }
+
+    println!("\nGenres");
+    for genre in &stats.genres {
+        println!("{:>4}  {:.2}  {}", genre.count, genre.average, genre.genre);
+    }
+
+    println!("\nTop releases");
+    for release in &stats.top_releases {
+        println!(
+            "{:>4}  {} - {}",
+            release.rating, release.artist_name, release.title
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn export(conn: &Connection, matches: &ArgMatches) -> Result<ExitCode> {
+    let format = Format::from_name(matches.get_one::<String>("format").unwrap()).unwrap();
+
+    let writer: Box<dyn Write> = match matches.get_one::<PathBuf>("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    if matches.get_flag("backlog") {
+        export::export_backlog(conn, format, writer)?;
+    } else {
+        export::export_ratings(conn, format, writer)?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn import(conn: &Connection, matches: &ArgMatches) -> Result<ExitCode> {
+    let path = matches.get_one::<PathBuf>("file").unwrap();
+    let policy =
+        ConflictPolicy::from_name(matches.get_one::<String>("on_conflict").unwrap()).unwrap();
+    let dry_run = matches.get_flag("dry_run");
+
+    let format = match matches.get_one::<String>("format") {
+        Some(name) => Format::from_name(name),
+        None => Format::from_path(path),
+    };
+
+    let Some(format) = format else {
+        eprintln!(
+            "Couldn't guess the format of {}, use --format to specify it",
+            path.display()
+        );
+        return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+    };
+
+    let reader = BufReader::new(File::open(path)?);
+    let summary = if matches.get_flag("backlog") {
+        import::import_backlog(conn, import::read_records(format, reader)?, dry_run)?
+    } else {
+        import::import_ratings(conn, import::read_records(format, reader)?, policy, dry_run)?
+    };
+
+    for change in &summary.changes {
+        println!("{change}");
+    }
+
+    let count = |is_kind: fn(&Change) -> bool| {
+        summary
+            .changes
+            .iter()
+            .filter(|change| is_kind(change))
+            .count()
+    };
+
+    println!(
+        "{}{} added, {} updated, {} retagged, {} unchanged, {} invalid",
+        if dry_run { "Dry run: " } else { "" },
+        count(|change| matches!(change, Change::Added(_) | Change::Queued(_))),
+        count(|change| matches!(change, Change::Updated { .. })),
+        count(|change| matches!(change, Change::Retagged { .. })),
+        summary.unchanged,
+        summary.invalid
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn import_rym(conn: &Connection, matches: &ArgMatches, cache: &Cache) -> Result<ExitCode> {
+    if cache.offline {
+        eprintln!("RateYourMusic ratings can't be matched to MusicBrainz offline");
+        return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+    }
+
+    let path = matches.get_one::<PathBuf>("file").unwrap();
+    let rows = rym::read_rows(BufReader::new(File::open(path)?))?;
+    let total = rows.len();
+
+    let mut matched = 0;
+    let mut already_rated = 0;
+    let mut queue = Vec::new();
+
+    for (idx, row) in rows.into_iter().enumerate() {
+        let description = row.to_string();
+
+        match rym::resolve(row).await {
+            Resolution::Matched(artist, release) => {
+                if rym::save_match(conn, &artist, &release)? {
+                    println!("[{}/{total}] {description}: {artist} - {release}", idx + 1);
+                    matched += 1;
+                } else {
+                    println!("[{}/{total}] {description}: already rated", idx + 1);
+                    already_rated += 1;
+                }
+            }
+            Resolution::Review(item) => {
+                match &item.error {
+                    Some(e) => eprintln!(
+                        "[{}/{total}] Couldn't look up {description}, left for review: {e}",
+                        idx + 1
+                    ),
+                    None => println!("[{}/{total}] {description}: needs review", idx + 1),
+                }
+
+                queue.push(item);
+            }
+        }
+    }
+
+    let mut review = Review::new(queue);
+
+    if !review.is_done() {
+        let mut terminal = crate::init_terminal()?;
+        let res = rym::run_review(&mut terminal, conn, &mut review).await;
+        crate::reset_terminal()?;
+        res?;
+    }
+
+    println!(
+        "{matched} matched, {} matched by hand, {already_rated} already rated, {} skipped",
+        review.matched,
+        review.skipped + review.queue.len() - review.current
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn show(conn: &Connection, matches: &ArgMatches, cache: &Cache) -> Result<ExitCode> {
+    let query = matches.get_one::<String>("artist").unwrap();
+
+    let Some(artist) = find_artist(query, cache).await? else {
+        eprintln!("No artists were found for \"{query}\"");
+        return Ok(ExitCode::from(EXIT_NOT_FOUND));
+    };
+
+    let key = cache::releases_key(&artist.id);
+    let mut releases = match cache.get::<Vec<Release>>(&key)? {
+        Some(cached) if !cached.stale || cache.offline => cached.value,
+        _ if cache.offline => {
+            eprintln!("The discography of {} isn't cached", artist.name);
+            return Ok(ExitCode::from(EXIT_NOT_FOUND));
+        }
+        _ => {
+            let releases = musicbrainz::fetch_releases(&artist.id).await?;
+            cache.put(&key, &releases)?;
+            releases
+        }
+    };
+
+    for (release_id, rating) in database::get_ratings(conn, &artist.id)? {
+        for release in &mut releases {
+            if release.id == release_id {
+                release.rating = Some(rating);
+            }
+        }
+    }
+
+    sort_releases(&mut releases);
+
+    println!("{artist}");
+
+    for item in insert_headers(releases) {
+        match item {
+            ListItemType::ReleaseType(release_type) => println!("\n{release_type}"),
+            ListItemType::Release(release) => match release.rating {
+                Some(rating) => println!("{rating:>4}  {release}"),
+                None => println!("{:>4}  {release}", "-"),
+            },
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Looks the artist up by MBID, or picks the best match of a search otherwise.
+async fn find_artist(query: &str, cache: &Cache) -> Result<Option<ArtistSearchResult>> {
+    if is_mbid(query) {
+        if cache.offline {
+            return Ok(None);
+        }
+
+        return Ok(musicbrainz::fetch_artist(query).await.ok());
+    }
+
+    let key = cache::search_key(query);
+    let results = match cache.get::<Vec<ArtistSearchResult>>(&key)? {
+        Some(cached) if !cached.stale || cache.offline => cached.value,
+        _ if cache.offline => Vec::new(),
+        _ => {
+            let results = musicbrainz::search_artist(query).await?;
+            cache.put(&key, &results)?;
+            results
+        }
+    };
+
+    Ok(results.into_iter().next())
+}
@@ -0,0 +1,400 @@
+use crate::utils::get_covers_dir;
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use icy_sixel::SixelImage;
+use image::{RgbaImage, imageops::FilterType};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use std::{
+    env,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::fs;
+
+const ARCHIVE_URL: &str = "https://coverartarchive.org/release-group";
+
+/// Release groups without a cover on the Cover Art Archive are only checked
+/// again after this long, as covers keep being added to it.
+const MISSING_COVER_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Size of a cell in pixels for terminals that don't report their size.
+const FALLBACK_CELL_SIZE: (u32, u32) = (10, 20);
+
+/// Deletes every image that was placed with the kitty graphics protocol.
+const KITTY_DELETE: &[u8] = b"\x1b_Ga=d,q=2\x1b\\";
+
+/// Where cover images are fetched from.
+#[derive(Clone)]
+pub enum CoverSource {
+    /// The front image of the release group on the Cover Art Archive.
+    Archive,
+    /// Images named after the MBID of their release group, such as
+    /// `<mbid>.jpg`, in a local directory.
+    Directory(PathBuf),
+}
+
+impl CoverSource {
+    /// Fetches the image of the release group, `None` if it doesn't have one.
+    async fn fetch(&self, release_id: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            CoverSource::Archive => {
+                let response =
+                    reqwest::get(format!("{ARCHIVE_URL}/{release_id}/front-250")).await?;
+
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+
+                Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+            }
+            CoverSource::Directory(dir) => {
+                for extension in ["jpg", "jpeg", "png"] {
+                    match fs::read(dir.join(format!("{release_id}.{extension}"))).await {
+                        Ok(bytes) => return Ok(Some(bytes)),
+                        Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Loads covers, keeping the ones fetched from the Cover Art Archive on disk.
+/// Covers don't change often, so unlike the MusicBrainz responses they are
+/// never considered stale. Release groups without a cover are remembered with
+/// an empty file.
+#[derive(Clone)]
+pub struct CoverFetcher {
+    source: CoverSource,
+    dir: PathBuf,
+    offline: bool,
+}
+
+impl CoverFetcher {
+    pub fn new(source: CoverSource, offline: bool) -> Result<Self> {
+        Ok(Self {
+            source,
+            dir: get_covers_dir()?,
+            offline,
+        })
+    }
+
+    pub async fn load(&self, release_id: &str) -> Result<Option<RgbaImage>> {
+        let bytes = match &self.source {
+            CoverSource::Archive => {
+                let path = self.dir.join(release_id);
+
+                match fs::read(&path).await {
+                    Ok(bytes) if !bytes.is_empty() => Some(bytes),
+                    Ok(_) if self.offline || !is_expired(&path).await? => None,
+                    Err(e) if e.kind() == ErrorKind::NotFound && self.offline => None,
+                    Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                    _ => {
+                        let bytes = self.source.fetch(release_id).await?;
+                        fs::write(&path, bytes.as_deref().unwrap_or_default()).await?;
+
+                        bytes
+                    }
+                }
+            }
+            CoverSource::Directory(_) => self.source.fetch(release_id).await?,
+        };
+
+        match bytes {
+            Some(bytes) => Ok(Some(image::load_from_memory(&bytes)?.into_rgba8())),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Whether the file of a missing cover is old enough to look for the cover
+/// again.
+async fn is_expired(path: &Path) -> Result<bool> {
+    let modified = fs::metadata(path).await?.modified()?;
+
+    Ok(SystemTime::now()
+        .duration_since(modified)
+        .is_ok_and(|age| age > MISSING_COVER_TTL))
+}
+
+pub enum Cover {
+    Loading,
+    Missing,
+    Image(RgbaImage),
+}
+
+/// How images are drawn in the terminal.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Graphics {
+    Kitty,
+    Sixel,
+    /// Two pixels per cell, drawn with the colors of `▀`. Works anywhere with
+    /// true color support.
+    HalfBlocks,
+}
+
+impl Graphics {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Graphics::detect()),
+            "kitty" => Some(Graphics::Kitty),
+            "sixel" => Some(Graphics::Sixel),
+            "halfblocks" => Some(Graphics::HalfBlocks),
+            _ => None,
+        }
+    }
+
+    /// Guesses the protocol from the environment. Terminals could be asked
+    /// instead, but their replies would have to be told apart from key
+    /// presses.
+    fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || matches!(term.as_str(), "xterm-kitty" | "xterm-ghostty")
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            Graphics::Kitty
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term == "mlterm"
+            || program == "iTerm.app"
+        {
+            Graphics::Sixel
+        } else {
+            Graphics::HalfBlocks
+        }
+    }
+}
+
+/// Where the cover of a release is shown.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CoverPlacement {
+    pub release_id: String,
+    pub area: Rect,
+}
+
+/// Draws covers with the kitty or sixel graphics on top of the rendered
+/// frame. The terminal keeps the image until it's deleted or drawn over, so
+/// it's only sent again when the placement changes.
+pub struct CoverPainter {
+    graphics: Graphics,
+    painted: Option<CoverPlacement>,
+}
+
+impl CoverPainter {
+    pub fn new(graphics: Graphics) -> Self {
+        Self {
+            graphics,
+            painted: None,
+        }
+    }
+
+    pub fn is_outdated(&self, placement: Option<&CoverPlacement>) -> bool {
+        self.graphics != Graphics::HalfBlocks && self.painted.as_ref() != placement
+    }
+
+    /// Sixel images can only be removed by redrawing the cells they cover.
+    pub fn needs_clear(&self) -> bool {
+        self.graphics == Graphics::Sixel && self.painted.is_some()
+    }
+
+    pub fn paint(
+        &mut self,
+        placement: Option<(&CoverPlacement, &RgbaImage)>,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        if self.graphics == Graphics::Kitty && self.painted.is_some() {
+            out.write_all(KITTY_DELETE)?;
+        }
+
+        if let Some((placement, image)) = placement {
+            let (cell_width, cell_height) = cell_size();
+            let image = fit(
+                image,
+                placement.area.width as u32 * cell_width,
+                placement.area.height as u32 * cell_height,
+            );
+
+            write!(
+                out,
+                "\x1b[{};{}H",
+                placement.area.y + 1,
+                placement.area.x + 1
+            )?;
+
+            match self.graphics {
+                Graphics::Kitty => write_kitty(&image, out)?,
+                Graphics::Sixel => {
+                    let (width, height) = image.dimensions();
+                    let sixel =
+                        SixelImage::from_rgba(image.into_raw(), width as usize, height as usize)
+                            .encode()?;
+                    out.write_all(sixel.as_bytes())?;
+                }
+                Graphics::HalfBlocks => (),
+            }
+        }
+
+        out.flush()?;
+        self.painted = placement.map(|(placement, _)| placement.clone());
+
+        Ok(())
+    }
+}
+
+/// Sends the image in chunks, as the kitty graphics protocol expects. `C=1`
+/// leaves the cursor where it was and `q=2` keeps the terminal from replying.
+fn write_kitty(image: &RgbaImage, out: &mut impl Write) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let data = STANDARD.encode(image.as_raw());
+    let mut chunks = data.as_bytes().chunks(4096).peekable();
+    let mut first = true;
+
+    while let Some(chunk) = chunks.next() {
+        let more = u8::from(chunks.peek().is_some());
+
+        if first {
+            write!(out, "\x1b_Ga=T,f=32,s={width},v={height},C=1,q=2,m={more};")?;
+            first = false;
+        } else {
+            write!(out, "\x1b_Gm={more};")?;
+        }
+
+        out.write_all(chunk)?;
+        out.write_all(b"\x1b\\")?;
+    }
+
+    Ok(())
+}
+
+/// Draws the image with two pixels in each cell, fitting it in the area.
+pub fn half_blocks(image: &RgbaImage, area: Rect) -> Vec<Line<'static>> {
+    let image = fit(image, area.width as u32, area.height as u32 * 2);
+    let color = |x, y| {
+        let [r, g, b, _] = image.get_pixel(x, y).0;
+        Color::Rgb(r, g, b)
+    };
+
+    (0..image.height())
+        .step_by(2)
+        .map(|y| {
+            (0..image.width())
+                .map(|x| {
+                    let mut style = Style::default().fg(color(x, y));
+
+                    if y + 1 < image.height() {
+                        style = style.bg(color(x, y + 1));
+                    }
+
+                    Span::styled("▀", style)
+                })
+                .collect::<Line>()
+        })
+        .collect()
+}
+
+/// Scales the image down or up to the largest size that fits in the box
+/// while keeping its aspect ratio.
+fn fit(image: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let scale = f64::min(
+        width as f64 / image.width() as f64,
+        height as f64 / image.height() as f64,
+    );
+    let scaled = |size: u32| ((size as f64 * scale).floor() as u32).max(1);
+
+    image::imageops::resize(
+        image,
+        scaled(image.width()),
+        scaled(image.height()),
+        FilterType::Triangle,
+    )
+}
+
+/// The size of a cell in pixels.
+fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns) as u32,
+            (size.height / size.rows) as u32,
+        ),
+        _ => FALLBACK_CELL_SIZE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+    use std::process;
+
+    const RELEASE_ID: &str = "22222222-2222-2222-2222-222222222222";
+
+    /// A fetcher that serves covers from a directory of its own, which is
+    /// also where it would keep covers from the Cover Art Archive.
+    fn fetcher(name: &str, source: impl FnOnce(PathBuf) -> CoverSource) -> CoverFetcher {
+        let dir = env::temp_dir().join(format!("rambt-{name}-{}", process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        CoverFetcher {
+            source: source(dir.clone()),
+            dir,
+            offline: true,
+        }
+    }
+
+    fn save_image(path: &Path) {
+        RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn loads_covers_from_a_directory() {
+        let fetcher = fetcher("directory", CoverSource::Directory);
+        save_image(&fetcher.dir.join(format!("{RELEASE_ID}.png")));
+
+        let image = fetcher.load(RELEASE_ID).await.unwrap().unwrap();
+        assert_eq!(image.dimensions(), (4, 2));
+
+        let missing = fetcher.load("33333333-3333-3333-3333-333333333333").await;
+        assert!(missing.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn serves_archive_covers_from_disk() {
+        let fetcher = fetcher("archive", |_| CoverSource::Archive);
+        let path = fetcher.dir.join(RELEASE_ID);
+
+        assert!(fetcher.load(RELEASE_ID).await.unwrap().is_none());
+
+        std::fs::write(&path, []).unwrap();
+        assert!(fetcher.load(RELEASE_ID).await.unwrap().is_none());
+        assert!(!is_expired(&path).await.unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+        save_image(&path.with_extension("png"));
+        std::fs::rename(path.with_extension("png"), &path).unwrap();
+        assert!(fetcher.load(RELEASE_ID).await.unwrap().is_some());
+    }
+
+    #[test]
+    fn draws_two_pixels_per_cell() {
+        let image = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 255, 255]));
+        let lines = half_blocks(&image, Rect::new(0, 0, 4, 10));
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans.len(), 4);
+        assert_eq!(lines[0].spans[0].style.bg, Some(Color::Rgb(0, 0, 255)));
+    }
+}
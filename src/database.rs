@@ -1,62 +1,531 @@
-use crate::{musicbrainz::Release, rating::Rated};
-use anyhow::Result;
-use rusqlite::{params, Connection};
+use crate::{
+    backlog::BacklogItem,
+    musicbrainz::{Release, Track},
+    rating::{Rated, RatingEvent},
+    utils::get_database_path,
+};
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
 
-pub fn initialize_db(conn: &Connection) -> Result<()> {
+/// Ordered schema migrations. The database's `user_version` is the number of
+/// migrations that have already been applied to it, so new steps must only
+/// ever be appended to this list.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE IF NOT EXISTS artists (
+        artist_id TEXT PRIMARY KEY,
+        artist_name TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS releases (
+        artist_id TEXT,
+        release_id TEXT PRIMARY KEY,
+        release_name TEXT,
+        year INTEGER,
+        rating INTEGER,
+        FOREIGN KEY(artist_id) REFERENCES artists(artist_id) ON DELETE CASCADE
+    );
+",
+    "
+    ALTER TABLE releases ADD COLUMN primary_type TEXT;
+    ALTER TABLE releases ADD COLUMN secondary_types TEXT;
+    ALTER TABLE releases ADD COLUMN first_release_date TEXT;
+",
+    "
+    ALTER TABLE releases ADD COLUMN rated_at INTEGER;
+",
+    "
+    CREATE TABLE IF NOT EXISTS rating_events (
+        event_id INTEGER PRIMARY KEY,
+        release_id TEXT,
+        old_rating INTEGER,
+        new_rating INTEGER,
+        changed_at INTEGER
+    );
+
+    CREATE INDEX rating_events_release_id ON rating_events(release_id);
+
+    INSERT INTO rating_events (release_id, old_rating, new_rating, changed_at)
+    SELECT release_id, NULL, rating, rated_at
+    FROM releases
+    WHERE rating IS NOT NULL;
+
+    CREATE TRIGGER rating_added AFTER INSERT ON releases
+    WHEN NEW.rating IS NOT NULL
+    BEGIN
+        INSERT INTO rating_events (release_id, old_rating, new_rating, changed_at)
+        VALUES(NEW.release_id, NULL, NEW.rating, CAST(strftime('%s', 'now') AS INTEGER));
+    END;
+
+    CREATE TRIGGER rating_changed AFTER UPDATE OF rating ON releases
+    WHEN OLD.rating IS NOT NEW.rating
+    BEGIN
+        INSERT INTO rating_events (release_id, old_rating, new_rating, changed_at)
+        VALUES(NEW.release_id, OLD.rating, NEW.rating, CAST(strftime('%s', 'now') AS INTEGER));
+    END;
+
+    CREATE TRIGGER rating_removed AFTER DELETE ON releases
+    WHEN OLD.rating IS NOT NULL
+    BEGIN
+        INSERT INTO rating_events (release_id, old_rating, new_rating, changed_at)
+        VALUES(OLD.release_id, OLD.rating, NULL, CAST(strftime('%s', 'now') AS INTEGER));
+    END;
+",
+    "
+    CREATE TABLE IF NOT EXISTS reviews (
+        release_id TEXT PRIMARY KEY,
+        body TEXT,
+        updated_at INTEGER,
+        FOREIGN KEY(release_id) REFERENCES releases(release_id) ON DELETE CASCADE
+    );
+",
+    "
+    CREATE TABLE IF NOT EXISTS track_ratings (
+        release_id TEXT,
+        recording_id TEXT,
+        title TEXT,
+        position INTEGER,
+        rating INTEGER,
+        PRIMARY KEY(release_id, recording_id)
+    );
+",
+    "
+    CREATE TABLE IF NOT EXISTS backlog (
+        release_id TEXT PRIMARY KEY,
+        artist_id TEXT,
+        artist_name TEXT,
+        release_name TEXT,
+        year INTEGER,
+        primary_type TEXT,
+        secondary_types TEXT,
+        first_release_date TEXT,
+        added_at INTEGER
+    );
+
+    CREATE TRIGGER backlog_rated AFTER INSERT ON releases
+    BEGIN
+        DELETE FROM backlog WHERE release_id = NEW.release_id;
+    END;
+",
+    "
+    ALTER TABLE releases ADD COLUMN community_rating REAL;
+    ALTER TABLE releases ADD COLUMN community_votes INTEGER;
+",
+    "
+    CREATE TABLE IF NOT EXISTS genres (
+        genre_id INTEGER PRIMARY KEY,
+        name TEXT UNIQUE
+    );
+
+    CREATE TABLE IF NOT EXISTS release_genres (
+        release_id TEXT,
+        genre_id INTEGER,
+        position INTEGER,
+        PRIMARY KEY(release_id, genre_id),
+        FOREIGN KEY(release_id) REFERENCES releases(release_id) ON DELETE CASCADE,
+        FOREIGN KEY(genre_id) REFERENCES genres(genre_id)
+    );
+",
+    "
+    CREATE TABLE IF NOT EXISTS tags (
+        tag_id INTEGER PRIMARY KEY,
+        name TEXT UNIQUE COLLATE NOCASE
+    );
+
+    CREATE TABLE IF NOT EXISTS release_tags (
+        release_id TEXT,
+        tag_id INTEGER,
+        PRIMARY KEY(release_id, tag_id),
+        FOREIGN KEY(tag_id) REFERENCES tags(tag_id)
+    );
+",
+    "
+    ALTER TABLE releases ADD COLUMN genres_fetched_at INTEGER;
+    ALTER TABLE artists ADD COLUMN genres_fetched_at INTEGER;
+
+    CREATE TABLE IF NOT EXISTS artist_genres (
+        artist_id TEXT,
+        genre_id INTEGER,
+        position INTEGER,
+        PRIMARY KEY(artist_id, genre_id),
+        FOREIGN KEY(artist_id) REFERENCES artists(artist_id) ON DELETE CASCADE,
+        FOREIGN KEY(genre_id) REFERENCES genres(genre_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS community_tags (
+        tag_id INTEGER PRIMARY KEY,
+        name TEXT UNIQUE
+    );
+
+    CREATE TABLE IF NOT EXISTS release_community_tags (
+        release_id TEXT,
+        tag_id INTEGER,
+        position INTEGER,
+        PRIMARY KEY(release_id, tag_id),
+        FOREIGN KEY(release_id) REFERENCES releases(release_id) ON DELETE CASCADE,
+        FOREIGN KEY(tag_id) REFERENCES community_tags(tag_id)
+    );
+
+    -- Release groups without genres used to get the genres of their artist,
+    -- which can't be told apart anymore, so every genre is fetched again.
+    DELETE FROM release_genres;
+",
+];
+
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+pub fn open_db() -> Result<Connection> {
+    let mut conn = Connection::open(get_database_path()?)?;
+    initialize_db(&mut conn)?;
+
+    Ok(conn)
+}
+
+pub fn initialize_db(conn: &mut Connection) -> Result<()> {
     conn.pragma_update(None, "foreign_keys", "on")?;
 
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    if version > SCHEMA_VERSION {
+        bail!(
+            "Database schema version {version} is newer than the supported version {SCHEMA_VERSION}. \
+             Please upgrade {}.",
+            env!("CARGO_PKG_NAME")
+        );
+    }
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)
+            .with_context(|| format!("Failed to migrate database to version {}", idx + 1))?;
+        tx.pragma_update(None, "user_version", idx + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+pub fn add_artist(conn: &Connection, artist_id: &str, artist_name: &str) -> Result<()> {
     conn.execute(
         "
-            CREATE TABLE IF NOT EXISTS artists (
-                artist_id TEXT PRIMARY KEY,
-                artist_name TEXT
-            )
+            INSERT OR IGNORE INTO artists (artist_id, artist_name)
+            VALUES(?1, ?2)
         ",
-        [],
+        params![artist_id, artist_name],
     )?;
 
+    Ok(())
+}
+
+/// Inserts or updates a rated release. The time of the rating is only
+/// updated when the rating actually changes, and the community rating is kept
+/// when the release doesn't come with one.
+pub fn add_release(conn: &Connection, artist_id: &str, release: &Release) -> Result<()> {
     conn.execute(
         "
-            CREATE TABLE IF NOT EXISTS releases (
-                artist_id TEXT,
-                release_id TEXT PRIMARY KEY,
-                release_name TEXT,
-                year INTEGER,
-                rating INTEGER,
-                FOREIGN KEY(artist_id) REFERENCES artists(artist_id) ON DELETE CASCADE
+            INSERT INTO releases (
+                artist_id, release_id, release_name, year, rating,
+                primary_type, secondary_types, first_release_date, rated_at,
+                community_rating, community_votes
             )
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CAST(strftime('%s', 'now') AS INTEGER), ?9, ?10)
+            ON CONFLICT(release_id) DO UPDATE SET
+                artist_id=excluded.artist_id,
+                release_name=excluded.release_name,
+                year=excluded.year,
+                rating=excluded.rating,
+                primary_type=excluded.primary_type,
+                secondary_types=excluded.secondary_types,
+                first_release_date=excluded.first_release_date,
+                community_rating=COALESCE(excluded.community_rating, releases.community_rating),
+                community_votes=COALESCE(excluded.community_votes, releases.community_votes),
+                rated_at=CASE
+                    WHEN releases.rating IS excluded.rating THEN releases.rated_at
+                    ELSE excluded.rated_at
+                END
         ",
-        [],
+        params![
+            artist_id,
+            release.id,
+            release.title,
+            release.known_year(),
+            release.rating,
+            release.group_type.primary_type(),
+            release.group_type.secondary_types(),
+            release.first_release_date,
+            release.community.map(|community| community.value),
+            release.community.map(|community| community.votes),
+        ],
     )?;
 
+    // Genres only come along with some of the ways a release is found, so
+    // they're only known to be fetched when there are any.
+    if !release.genres.is_empty() {
+        update_release_genres(conn, &release.id, &release.genres, &release.community_tags)?;
+    }
+
     Ok(())
 }
 
-pub fn add_artist(conn: &Connection, artist_id: &str, artist_name: &str) -> Result<()> {
+/// Names from MusicBrainz that are linked to rated releases or artists in
+/// order, the most voted first.
+struct Links {
+    /// The table that links the names to their owners.
+    table: &'static str,
+    owner_column: &'static str,
+    /// The table of the names, shared between the owners.
+    names: &'static str,
+    name_column: &'static str,
+}
+
+const RELEASE_GENRES: Links = Links {
+    table: "release_genres",
+    owner_column: "release_id",
+    names: "genres",
+    name_column: "genre_id",
+};
+
+const ARTIST_GENRES: Links = Links {
+    table: "artist_genres",
+    owner_column: "artist_id",
+    names: "genres",
+    name_column: "genre_id",
+};
+
+const RELEASE_COMMUNITY_TAGS: Links = Links {
+    table: "release_community_tags",
+    owner_column: "release_id",
+    names: "community_tags",
+    name_column: "tag_id",
+};
+
+/// Replaces the names linked to an owner, keeping their order.
+fn set_links(conn: &Connection, links: &Links, owner_id: &str, names: &[String]) -> Result<()> {
+    let Links {
+        table,
+        owner_column,
+        names: names_table,
+        name_column,
+    } = links;
+
     conn.execute(
+        &format!("DELETE FROM {table} WHERE {owner_column}=?1"),
+        params![owner_id],
+    )?;
+
+    for (position, name) in names.iter().enumerate() {
+        conn.execute(
+            &format!("INSERT OR IGNORE INTO {names_table} (name) VALUES(?1)"),
+            params![name],
+        )?;
+
+        conn.execute(
+            &format!(
+                "
+                    INSERT OR IGNORE INTO {table} ({owner_column}, {name_column}, position)
+                    SELECT ?1, {name_column}, ?3
+                    FROM {names_table}
+                    WHERE name=?2
+                "
+            ),
+            params![owner_id, name, position],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The names linked to the given owner, or to every owner, keyed by the
+/// owner.
+fn get_links(
+    conn: &Connection,
+    links: &Links,
+    owner_id: Option<&str>,
+) -> Result<HashMap<String, Vec<String>>> {
+    let Links {
+        table,
+        owner_column,
+        names: names_table,
+        name_column,
+    } = links;
+
+    let mut stmt = conn.prepare(&format!(
         "
-            INSERT OR IGNORE INTO artists (artist_id, artist_name)
-            VALUES(?1, ?2)
+            SELECT {owner_column}, name
+            FROM {table}
+            INNER JOIN {names_table} ON {names_table}.{name_column} = {table}.{name_column}
+            WHERE ?1 IS NULL OR {owner_column}=?1
+            ORDER BY {owner_column}, position
+        "
+    ))?;
+
+    let mut names = HashMap::<String, Vec<String>>::new();
+
+    for row in stmt.query_map(params![owner_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })? {
+        let (owner_id, name) = row?;
+        names.entry(owner_id).or_default().push(name);
+    }
+
+    Ok(names)
+}
+
+/// Stores freshly fetched genres and community tags of a rated release, and
+/// remembers that they were fetched so that releases nobody gave a genre to
+/// aren't fetched again. Empty lists keep what the release has.
+pub fn update_release_genres(
+    conn: &Connection,
+    release_id: &str,
+    genres: &[String],
+    community_tags: &[String],
+) -> Result<()> {
+    if !genres.is_empty() {
+        set_links(conn, &RELEASE_GENRES, release_id, genres)?;
+    }
+
+    if !community_tags.is_empty() {
+        set_links(conn, &RELEASE_COMMUNITY_TAGS, release_id, community_tags)?;
+    }
+
+    conn.execute(
+        "
+            UPDATE releases
+            SET genres_fetched_at=CAST(strftime('%s', 'now') AS INTEGER)
+            WHERE release_id=?1
         ",
-        params![artist_id, artist_name],
+        params![release_id],
     )?;
 
     Ok(())
 }
 
-pub fn add_release(conn: &Connection, artist_id: &str, release: &Release) -> Result<()> {
+/// Stores freshly fetched genres of a rated artist, which are kept apart from
+/// the genres of its release groups. Like the genres of releases, empty
+/// lists keep what the artist has.
+pub fn update_artist_genres(conn: &Connection, artist_id: &str, genres: &[String]) -> Result<()> {
+    if !genres.is_empty() {
+        set_links(conn, &ARTIST_GENRES, artist_id, genres)?;
+    }
+
     conn.execute(
         "
-            INSERT OR REPLACE INTO releases (artist_id, release_id, release_name, rating)
-            VALUES(?, ?, ?, ?)
+            UPDATE artists
+            SET genres_fetched_at=CAST(strftime('%s', 'now') AS INTEGER)
+            WHERE artist_id=?1
+        ",
+        params![artist_id],
+    )?;
 
+    Ok(())
+}
+
+/// The genres of the artist of a rated release.
+pub fn get_artist_genres(conn: &Connection, release_id: &str) -> Result<Vec<String>> {
+    let artist_id = conn
+        .query_row(
+            "
+                SELECT artist_id
+                FROM releases
+                WHERE release_id=?1
+            ",
+            params![release_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+
+    let Some(artist_id) = artist_id else {
+        return Ok(Vec::new());
+    };
+
+    Ok(get_links(conn, &ARTIST_GENRES, Some(&artist_id))?
+        .remove(&artist_id)
+        .unwrap_or_default())
+}
+
+/// Rated artists whose genres were never fetched.
+pub fn get_artists_missing_genres(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT artist_id
+            FROM artists
+            WHERE genres_fetched_at IS NULL
+        ",
+    )?;
+
+    let mut artist_ids = Vec::new();
+
+    for artist_id in stmt.query_map(params![], |row| row.get(0))? {
+        artist_ids.push(artist_id?);
+    }
+
+    Ok(artist_ids)
+}
+
+/// Overrides the time a release was rated at, e.g. with the one of an import.
+pub fn set_rated_at(conn: &Connection, release_id: &str, rated_at: i64) -> Result<()> {
+    conn.execute(
+        "
+            UPDATE releases
+            SET rated_at=?2
+            WHERE release_id=?1
         ",
-        params![artist_id, release.id, release.title, release.rating],
+        params![release_id, rated_at],
     )?;
 
     Ok(())
 }
 
+/// Updates a rated release with freshly fetched metadata. Releases nobody
+/// rated on MusicBrainz get 0 votes, so that they aren't fetched again.
+pub fn update_release_metadata(conn: &Connection, release: &Release) -> Result<()> {
+    conn.execute(
+        "
+            UPDATE releases
+            SET release_name=?2, year=?3, primary_type=?4, secondary_types=?5, first_release_date=?6,
+                community_rating=?7, community_votes=?8
+            WHERE release_id=?1
+        ",
+        params![
+            release.id,
+            release.title,
+            release.known_year(),
+            release.group_type.primary_type(),
+            release.group_type.secondary_types(),
+            release.first_release_date,
+            release.community.map(|community| community.value),
+            release.community.map_or(0, |community| community.votes),
+        ],
+    )?;
+
+    update_release_genres(conn, &release.id, &release.genres, &release.community_tags)?;
+
+    Ok(())
+}
+
+/// Releases without a known type, release date or community rating, or whose
+/// genres were never fetched, most likely rated before the metadata was
+/// stored alongside the rating.
+pub fn get_releases_missing_metadata(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT release_id
+            FROM releases
+            WHERE primary_type IS NULL OR first_release_date IS NULL OR community_votes IS NULL
+                OR genres_fetched_at IS NULL
+        ",
+    )?;
+
+    let mut release_ids = Vec::new();
+
+    for release_id in stmt.query_map(params![], |row| row.get(0))? {
+        release_ids.push(release_id?);
+    }
+
+    Ok(release_ids)
+}
+
 pub fn get_ratings(conn: &Connection, artist_id: &str) -> Result<Vec<(String, u8)>> {
     let mut stmt = conn.prepare(
         "
@@ -75,21 +544,602 @@ pub fn get_ratings(conn: &Connection, artist_id: &str) -> Result<Vec<(String, u8
     Ok(ratings)
 }
 
+/// Replaces our tags of a release, which can be rated or in the backlog.
+/// Tags are kept when the rating is removed, in case it's undone. Tags that
+/// no release has anymore are dropped so that they aren't suggested.
+pub fn set_tags(conn: &Connection, release_id: &str, tags: &[String]) -> Result<()> {
+    conn.execute(
+        "
+            DELETE FROM release_tags
+            WHERE release_id=?1
+        ",
+        params![release_id],
+    )?;
+
+    for tag in tags {
+        conn.execute(
+            "
+                INSERT OR IGNORE INTO tags (name)
+                VALUES(?1)
+            ",
+            params![tag],
+        )?;
+
+        conn.execute(
+            "
+                INSERT OR IGNORE INTO release_tags (release_id, tag_id)
+                SELECT ?1, tag_id
+                FROM tags
+                WHERE name=?2
+            ",
+            params![release_id, tag],
+        )?;
+    }
+
+    conn.execute(
+        "
+            DELETE FROM tags
+            WHERE NOT EXISTS (SELECT 1 FROM release_tags WHERE tag_id = tags.tag_id)
+        ",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_tags(conn: &Connection, release_id: &str) -> Result<Vec<String>> {
+    Ok(get_release_tags(conn, Some(release_id))?
+        .remove(release_id)
+        .unwrap_or_default())
+}
+
+/// The tags of the given release, or of every release, keyed by the release.
+fn get_release_tags(
+    conn: &Connection,
+    release_id: Option<&str>,
+) -> Result<HashMap<String, Vec<String>>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT release_id, name
+            FROM release_tags
+            INNER JOIN tags ON tags.tag_id = release_tags.tag_id
+            WHERE ?1 IS NULL OR release_id=?1
+            ORDER BY release_id, name COLLATE NOCASE
+        ",
+    )?;
+
+    let mut tags = HashMap::<String, Vec<String>>::new();
+
+    for row in stmt.query_map(params![release_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })? {
+        let (release_id, tag) = row?;
+        tags.entry(release_id).or_default().push(tag);
+    }
+
+    Ok(tags)
+}
+
+/// Every tag that is in use, the most used first.
+pub fn get_tag_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT name
+            FROM tags
+            INNER JOIN release_tags ON release_tags.tag_id = tags.tag_id
+            GROUP BY tags.tag_id
+            ORDER BY COUNT(*) DESC, name COLLATE NOCASE
+        ",
+    )?;
+
+    let mut names = Vec::new();
+
+    for name in stmt.query_map(params![], |row| row.get(0))? {
+        names.push(name?);
+    }
+
+    Ok(names)
+}
+
+pub fn get_rating(conn: &Connection, release_id: &str) -> Result<Option<Rated>> {
+    let mut rating = conn
+        .query_row(
+            "
+                SELECT artists.artist_id, artists.artist_name, releases.release_id, release_name, rating,
+                    year, primary_type, secondary_types, first_release_date, rated_at, reviews.body,
+                (SELECT AVG(rating) FROM track_ratings WHERE release_id = releases.release_id),
+                community_rating, community_votes
+                FROM releases
+                INNER JOIN artists ON artists.artist_id = releases.artist_id
+                LEFT JOIN reviews ON reviews.release_id = releases.release_id
+                WHERE releases.release_id=?1
+            ",
+            params![release_id],
+            |row| Rated::try_from(row),
+        )
+        .optional()?;
+
+    if let Some(rating) = &mut rating {
+        rating.genres = get_links(conn, &RELEASE_GENRES, Some(release_id))?
+            .remove(release_id)
+            .unwrap_or_default();
+        rating.artist_genres = get_links(conn, &ARTIST_GENRES, Some(&rating.artist_id))?
+            .remove(&rating.artist_id)
+            .unwrap_or_default();
+        rating.community_tags = get_links(conn, &RELEASE_COMMUNITY_TAGS, Some(release_id))?
+            .remove(release_id)
+            .unwrap_or_default();
+        rating.tags = get_tags(conn, release_id)?;
+    }
+
+    Ok(rating)
+}
+
+/// Removes the rating of a release, along with its artist if it was the last
+/// rated release of the artist. Returns whether the release was rated.
+pub fn delete_release(conn: &Connection, release_id: &str) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+
+    let artist_id = tx
+        .query_row(
+            "
+                SELECT artist_id
+                FROM releases
+                WHERE release_id=?1
+            ",
+            params![release_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+
+    let Some(artist_id) = artist_id else {
+        return Ok(false);
+    };
+
+    tx.execute(
+        "
+            DELETE FROM releases
+            WHERE release_id=?1
+        ",
+        params![release_id],
+    )?;
+
+    tx.execute(
+        "
+            DELETE FROM artists
+            WHERE artist_id=?1
+                AND NOT EXISTS (SELECT 1 FROM releases WHERE artist_id=?1)
+        ",
+        params![artist_id],
+    )?;
+
+    tx.commit()?;
+
+    Ok(true)
+}
+
 pub fn get_every_rating(conn: &Connection) -> Result<Vec<Rated>> {
     let mut stmt = conn.prepare(
         "
-            SELECT artists.artist_id, artists.artist_name, release_id, release_name, rating
+            SELECT artists.artist_id, artists.artist_name, releases.release_id, release_name, rating,
+                year, primary_type, secondary_types, first_release_date, rated_at, reviews.body,
+                (SELECT AVG(rating) FROM track_ratings WHERE release_id = releases.release_id),
+                community_rating, community_votes
             FROM releases
             INNER JOIN artists ON artists.artist_id = releases.artist_id
+            LEFT JOIN reviews ON reviews.release_id = releases.release_id
             ORDER BY rating DESC
         ",
     )?;
 
     let mut ratings = Vec::new();
 
+    let mut genres = get_links(conn, &RELEASE_GENRES, None)?;
+    let artist_genres = get_links(conn, &ARTIST_GENRES, None)?;
+    let mut community_tags = get_links(conn, &RELEASE_COMMUNITY_TAGS, None)?;
+    let mut tags = get_release_tags(conn, None)?;
+
     for rating in stmt.query_map(params![], |row| Rated::try_from(row))? {
+        let mut rating = rating?;
+        rating.genres = genres.remove(&rating.release_id).unwrap_or_default();
+        rating.artist_genres = artist_genres
+            .get(&rating.artist_id)
+            .cloned()
+            .unwrap_or_default();
+        rating.community_tags = community_tags
+            .remove(&rating.release_id)
+            .unwrap_or_default();
+        rating.tags = tags.remove(&rating.release_id).unwrap_or_default();
+        ratings.push(rating);
+    }
+
+    Ok(ratings)
+}
+
+/// Stores the rating of a track of a release group. Tracks are rated
+/// independently of the release group, which doesn't have to be rated.
+pub fn set_track_rating(
+    conn: &Connection,
+    release_id: &str,
+    position: usize,
+    track: &Track,
+) -> Result<()> {
+    conn.execute(
+        "
+            INSERT INTO track_ratings (release_id, recording_id, title, position, rating)
+            VALUES(?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(release_id, recording_id) DO UPDATE SET
+                title=excluded.title,
+                position=excluded.position,
+                rating=excluded.rating
+        ",
+        params![release_id, track.id, track.title, position, track.rating],
+    )?;
+
+    Ok(())
+}
+
+pub fn delete_track_rating(conn: &Connection, release_id: &str, recording_id: &str) -> Result<()> {
+    conn.execute(
+        "
+            DELETE FROM track_ratings
+            WHERE release_id=?1 AND recording_id=?2
+        ",
+        params![release_id, recording_id],
+    )?;
+
+    Ok(())
+}
+
+pub fn get_track_ratings(conn: &Connection, release_id: &str) -> Result<Vec<(String, u8)>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT recording_id, rating
+            FROM track_ratings
+            WHERE release_id=?1
+        ",
+    )?;
+
+    let mut ratings = Vec::new();
+
+    for rating in stmt.query_map(params![release_id], |row| Ok((row.get(0)?, row.get(1)?)))? {
         ratings.push(rating?);
     }
 
     Ok(ratings)
 }
+
+/// The average track rating of every release group with rated tracks.
+pub fn get_track_averages(conn: &Connection) -> Result<Vec<(String, f64)>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT release_id, AVG(rating)
+            FROM track_ratings
+            GROUP BY release_id
+        ",
+    )?;
+
+    let mut averages = Vec::new();
+
+    for average in stmt.query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))? {
+        averages.push(average?);
+    }
+
+    Ok(averages)
+}
+
+pub fn get_review(conn: &Connection, release_id: &str) -> Result<Option<String>> {
+    let review = conn
+        .query_row(
+            "
+                SELECT body
+                FROM reviews
+                WHERE release_id=?1
+            ",
+            params![release_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(review)
+}
+
+/// Stores the review of a rated release. An empty review removes it.
+pub fn set_review(conn: &Connection, release_id: &str, body: &str) -> Result<()> {
+    if body.is_empty() {
+        conn.execute(
+            "
+                DELETE FROM reviews
+                WHERE release_id=?1
+            ",
+            params![release_id],
+        )?;
+    } else {
+        conn.execute(
+            "
+                INSERT INTO reviews (release_id, body, updated_at)
+                VALUES(?1, ?2, CAST(strftime('%s', 'now') AS INTEGER))
+                ON CONFLICT(release_id) DO UPDATE SET
+                    body=excluded.body,
+                    updated_at=excluded.updated_at
+            ",
+            params![release_id, body],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Changes to ratings, oldest first. The names are missing for releases whose
+/// rating has been removed since.
+pub fn get_rating_events(
+    conn: &Connection,
+    release_id: Option<&str>,
+    since: Option<i64>,
+) -> Result<Vec<RatingEvent>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT rating_events.release_id, artists.artist_name, releases.release_name,
+                old_rating, new_rating, changed_at
+            FROM rating_events
+            LEFT JOIN releases ON releases.release_id = rating_events.release_id
+            LEFT JOIN artists ON artists.artist_id = releases.artist_id
+            WHERE (?1 IS NULL OR rating_events.release_id=?1)
+                AND (?2 IS NULL OR changed_at >= ?2)
+            ORDER BY changed_at, event_id
+        ",
+    )?;
+
+    let mut events = Vec::new();
+
+    for event in stmt.query_map(params![release_id, since], |row| RatingEvent::try_from(row))? {
+        events.push(event?);
+    }
+
+    Ok(events)
+}
+
+pub fn get_last_rating_event(conn: &Connection, release_id: &str) -> Result<Option<RatingEvent>> {
+    let event = conn
+        .query_row(
+            "
+                SELECT rating_events.release_id, artists.artist_name, releases.release_name,
+                    old_rating, new_rating, changed_at
+                FROM rating_events
+                LEFT JOIN releases ON releases.release_id = rating_events.release_id
+                LEFT JOIN artists ON artists.artist_id = releases.artist_id
+                WHERE rating_events.release_id=?1
+                ORDER BY changed_at DESC, event_id DESC
+                LIMIT 1
+            ",
+            params![release_id],
+            |row| RatingEvent::try_from(row),
+        )
+        .optional()?;
+
+    Ok(event)
+}
+
+/// Returns whether the release was added, which it isn't if it was in the
+/// backlog already.
+pub fn add_to_backlog(
+    conn: &Connection,
+    artist_id: &str,
+    artist_name: &str,
+    release: &Release,
+) -> Result<bool> {
+    let added = conn.execute(
+        "
+            INSERT OR IGNORE INTO backlog (
+                release_id, artist_id, artist_name, release_name, year,
+                primary_type, secondary_types, first_release_date, added_at
+            )
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CAST(strftime('%s', 'now') AS INTEGER))
+        ",
+        params![
+            release.id,
+            artist_id,
+            artist_name,
+            release.title,
+            release.known_year(),
+            release.group_type.primary_type(),
+            release.group_type.secondary_types(),
+            release.first_release_date,
+        ],
+    )?;
+
+    Ok(added > 0)
+}
+
+/// Overrides the time a release was added to the backlog at, e.g. with the
+/// one of an import.
+pub fn set_added_at(conn: &Connection, release_id: &str, added_at: i64) -> Result<()> {
+    conn.execute(
+        "
+            UPDATE backlog
+            SET added_at=?2
+            WHERE release_id=?1
+        ",
+        params![release_id, added_at],
+    )?;
+
+    Ok(())
+}
+
+/// Returns whether the release was in the backlog.
+pub fn remove_from_backlog(conn: &Connection, release_id: &str) -> Result<bool> {
+    let removed = conn.execute(
+        "
+            DELETE FROM backlog
+            WHERE release_id=?1
+        ",
+        params![release_id],
+    )?;
+
+    Ok(removed > 0)
+}
+
+pub fn get_backlog(conn: &Connection) -> Result<Vec<BacklogItem>> {
+    let mut stmt = conn.prepare(
+        "
+            SELECT artist_id, artist_name, release_id, release_name, year,
+                primary_type, secondary_types, first_release_date, added_at
+            FROM backlog
+            ORDER BY added_at
+        ",
+    )?;
+
+    let mut backlog = Vec::new();
+    let mut tags = get_release_tags(conn, None)?;
+
+    for item in stmt.query_map(params![], |row| BacklogItem::try_from(row))? {
+        let mut item = item?;
+        item.tags = tags.remove(&item.release_id).unwrap_or_default();
+        backlog.push(item);
+    }
+
+    Ok(backlog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ReleaseType;
+
+    const ARTIST_ID: &str = "11111111-1111-1111-1111-111111111111";
+    const RELEASE_ID: &str = "22222222-2222-2222-2222-222222222222";
+
+    fn open() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        initialize_db(&mut conn).unwrap();
+        conn
+    }
+
+    /// A connection with only the first `version` migrations applied.
+    fn open_at(version: usize) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+
+        for migration in &MIGRATIONS[..version] {
+            conn.execute_batch(migration).unwrap();
+        }
+
+        conn.pragma_update(None, "user_version", version).unwrap();
+        conn
+    }
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn migrates_every_version() {
+        for version in 0..=SCHEMA_VERSION {
+            let mut conn = open_at(version);
+            initialize_db(&mut conn).unwrap();
+
+            assert_eq!(user_version(&conn), SCHEMA_VERSION);
+        }
+    }
+
+    #[test]
+    fn keeps_ratings_from_the_first_version() {
+        let mut conn = open_at(1);
+        conn.execute_batch(&format!(
+            "
+                INSERT INTO artists VALUES('{ARTIST_ID}', 'Artist');
+                INSERT INTO releases VALUES('{ARTIST_ID}', '{RELEASE_ID}', 'Title', 1999, 7);
+            "
+        ))
+        .unwrap();
+
+        initialize_db(&mut conn).unwrap();
+
+        let ratings = get_every_rating(&conn).unwrap();
+        assert_eq!(ratings.len(), 1);
+        assert_eq!(ratings[0].release_id, RELEASE_ID);
+        assert_eq!(ratings[0].rating, Some(7));
+        assert_eq!(
+            get_rating_events(&conn, Some(RELEASE_ID), None)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(get_releases_missing_metadata(&conn).unwrap(), [RELEASE_ID]);
+    }
+
+    #[test]
+    fn initializes_only_once() {
+        let mut conn = open();
+        initialize_db(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn refuses_newer_databases() {
+        let mut conn = open_at(0);
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        assert!(initialize_db(&mut conn).is_err());
+    }
+
+    fn release(genres: &[&str]) -> Release {
+        Release {
+            id: RELEASE_ID.to_string(),
+            title: "Title".to_string(),
+            year: 1999,
+            first_release_date: Some("1999-01-01".to_string()),
+            group_type: ReleaseType::parse("Album"),
+            community: None,
+            genres: genres.iter().map(|genre| genre.to_string()).collect(),
+            community_tags: Vec::new(),
+            rating: Some(7),
+            track_average: None,
+            in_backlog: false,
+        }
+    }
+
+    fn rate(conn: &Connection, release: &Release) {
+        add_artist(conn, ARTIST_ID, "Artist").unwrap();
+        add_release(conn, ARTIST_ID, release).unwrap();
+    }
+
+    #[test]
+    fn fetches_genres_only_once() {
+        let conn = open();
+        rate(&conn, &release(&[]));
+        assert_eq!(get_releases_missing_metadata(&conn).unwrap(), [RELEASE_ID]);
+
+        update_release_metadata(&conn, &release(&[])).unwrap();
+        assert!(get_releases_missing_metadata(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_genres_when_none_are_fetched() {
+        let conn = open();
+        rate(&conn, &release(&["rock", "pop"]));
+
+        update_release_metadata(&conn, &release(&[])).unwrap();
+
+        let rating = get_rating(&conn, RELEASE_ID).unwrap().unwrap();
+        assert_eq!(rating.genres, ["rock", "pop"]);
+    }
+
+    #[test]
+    fn keeps_artist_genres_apart() {
+        let conn = open();
+        rate(&conn, &release(&[]));
+        assert_eq!(get_artists_missing_genres(&conn).unwrap(), [ARTIST_ID]);
+
+        update_artist_genres(&conn, ARTIST_ID, &["jazz".to_string()]).unwrap();
+
+        let rating = get_rating(&conn, RELEASE_ID).unwrap().unwrap();
+        assert!(rating.genres.is_empty());
+        assert_eq!(rating.artist_genres, ["jazz"]);
+        assert_eq!(rating.effective_genres(), ["jazz"]);
+        assert_eq!(get_artist_genres(&conn, RELEASE_ID).unwrap(), ["jazz"]);
+        assert!(get_artists_missing_genres(&conn).unwrap().is_empty());
+    }
+}
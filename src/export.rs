@@ -0,0 +1,120 @@
+use crate::{backlog::BacklogRecord, database, rating::Rated, utils::join_tags};
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::{io::Write, path::Path};
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    /// Guesses the format from the extension of the file.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(&path.extension()?.to_str()?.to_lowercase())
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            _ => None,
+        }
+    }
+
+    pub fn delimiter(self) -> u8 {
+        match self {
+            Format::Tsv => b'\t',
+            _ => b',',
+        }
+    }
+}
+
+/// A single rating as it is written to an export. The order of the fields is
+/// the column order of the CSV and TSV exports, so new fields must only be
+/// appended.
+#[derive(Serialize, Deserialize)]
+pub struct Record {
+    pub artist_id: String,
+    pub artist_name: String,
+    pub release_id: String,
+    pub title: String,
+    pub year: Option<i32>,
+    #[serde(rename = "type")]
+    pub release_type: String,
+    pub rating: Option<u8>,
+    /// Unix timestamp of the last time the rating changed. Missing from
+    /// exports of older versions.
+    #[serde(default)]
+    pub rated_at: Option<i64>,
+    #[serde(default)]
+    pub review: Option<String>,
+    /// Our tags, separated by commas.
+    #[serde(default)]
+    pub tags: Option<String>,
+}
+
+impl From<Rated> for Record {
+    fn from(value: Rated) -> Self {
+        Record {
+            artist_id: value.artist_id,
+            artist_name: value.artist_name,
+            release_id: value.release_id,
+            title: value.title,
+            year: value.year,
+            release_type: value.group_type.to_string(),
+            rating: value.rating,
+            rated_at: value.rated_at,
+            review: value.review,
+            tags: (!value.tags.is_empty()).then(|| join_tags(&value.tags)),
+        }
+    }
+}
+
+pub fn export_ratings(conn: &Connection, format: Format, writer: impl Write) -> Result<()> {
+    let records = database::get_every_rating(conn)?
+        .into_iter()
+        .map(Record::from)
+        .collect::<Vec<Record>>();
+
+    write_records(&records, format, writer)
+}
+
+pub fn export_backlog(conn: &Connection, format: Format, writer: impl Write) -> Result<()> {
+    let records = database::get_backlog(conn)?
+        .into_iter()
+        .map(BacklogRecord::from)
+        .collect::<Vec<BacklogRecord>>();
+
+    write_records(&records, format, writer)
+}
+
+fn write_records<T: Serialize>(
+    records: &[T],
+    format: Format,
+    mut writer: impl Write,
+) -> Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, records)?;
+            writeln!(writer)?;
+        }
+        Format::Csv | Format::Tsv => {
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(format.delimiter())
+                .from_writer(writer);
+
+            for record in records {
+                writer.serialize(record)?;
+            }
+
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
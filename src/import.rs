@@ -0,0 +1,570 @@
+use crate::{
+    app::ReleaseType,
+    backlog::BacklogRecord,
+    database,
+    export::{Format, Record},
+    musicbrainz::Release,
+    rating::Rated,
+    utils::{is_mbid, join_tags, parse_tags},
+};
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use std::{fmt::Display, io::Read};
+
+/// What to do with a release that is rated both in the database and in the
+/// imported file, but with a different rating.
+#[derive(Clone, Copy)]
+pub enum ConflictPolicy {
+    /// Keep the rating in the database.
+    Keep,
+    /// Always take the imported rating.
+    Overwrite,
+    /// Take the higher of the two ratings.
+    Max,
+    /// Take the rating that was changed most recently.
+    Newest,
+}
+
+impl ConflictPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keep" => Some(ConflictPolicy::Keep),
+            "overwrite" => Some(ConflictPolicy::Overwrite),
+            "max" => Some(ConflictPolicy::Max),
+            "newest" => Some(ConflictPolicy::Newest),
+            _ => None,
+        }
+    }
+
+    fn prefers_import(self, existing: &Rated, record: &Record) -> bool {
+        match self {
+            ConflictPolicy::Keep => false,
+            ConflictPolicy::Overwrite => true,
+            ConflictPolicy::Max => record.rating > existing.rating,
+            ConflictPolicy::Newest => record.rated_at > existing.rated_at,
+        }
+    }
+}
+
+pub enum Change {
+    Added(Record),
+    Updated {
+        old: Box<Rated>,
+        new: Record,
+    },
+    /// A release that was added to the backlog.
+    Queued(BacklogRecord),
+    /// A release of which only our tags changed.
+    Retagged {
+        release: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tags = |tags: &[String]| {
+            if tags.is_empty() {
+                "no tags".to_string()
+            } else {
+                join_tags(tags)
+            }
+        };
+
+        match self {
+            Change::Added(record) => write!(
+                f,
+                "+ {}: {}/10",
+                describe(&record.artist_name, record.year, &record.title),
+                record.rating.unwrap_or_default()
+            ),
+            Change::Updated { old, new } => write!(
+                f,
+                "~ {old}: {}/10 -> {}/10",
+                old.rating.unwrap_or_default(),
+                new.rating.unwrap_or_default()
+            ),
+            Change::Queued(record) => write!(
+                f,
+                "+ {}",
+                describe(&record.artist_name, record.year, &record.title)
+            ),
+            Change::Retagged { release, old, new } => {
+                write!(f, "# {release}: {} -> {}", tags(old), tags(new))
+            }
+        }
+    }
+}
+
+fn describe(artist_name: &str, year: Option<i32>, title: &str) -> String {
+    match year {
+        Some(year) => format!("{artist_name} - ({year}) {title}"),
+        None => format!("{artist_name} - {title}"),
+    }
+}
+
+#[derive(Default)]
+pub struct ImportSummary {
+    pub changes: Vec<Change>,
+    pub unchanged: usize,
+    pub invalid: usize,
+}
+
+/// Reads the records of a rating or a backlog export.
+pub fn read_records<T: DeserializeOwned>(format: Format, reader: impl Read) -> Result<Vec<T>> {
+    match format {
+        Format::Json => serde_json::from_reader(reader).context("Couldn't parse the JSON file"),
+        Format::Csv | Format::Tsv => csv::ReaderBuilder::new()
+            .delimiter(format.delimiter())
+            .from_reader(reader)
+            .deserialize()
+            .collect::<Result<Vec<T>, _>>()
+            .context("Couldn't parse the file"),
+    }
+}
+
+/// Merges the records into the database. Every change is made in a single
+/// transaction, which is rolled back instead of committed on a dry run so that
+/// the summary is the same either way.
+pub fn import_ratings(
+    conn: &Connection,
+    records: Vec<Record>,
+    policy: ConflictPolicy,
+    dry_run: bool,
+) -> Result<ImportSummary> {
+    let tx = conn.unchecked_transaction()?;
+    let mut summary = ImportSummary::default();
+
+    for (idx, record) in records.into_iter().enumerate() {
+        if !is_valid(&record) {
+            eprintln!(
+                "Skipping record {}: \"{} - {}\" doesn't have valid identifiers and a rating from 1 to 10",
+                idx + 1,
+                record.artist_name,
+                record.title
+            );
+            summary.invalid += 1;
+            continue;
+        }
+
+        let existing = database::get_rating(&tx, &record.release_id)?;
+        let has_review = existing
+            .as_ref()
+            .is_some_and(|existing| existing.review.is_some());
+        // Releases that are rated differently and keep their rating keep their
+        // tags as well.
+        let is_kept = existing.as_ref().is_some_and(|existing| {
+            existing.rating != record.rating && !policy.prefers_import(existing, &record)
+        });
+        let release = describe(&record.artist_name, record.year, &record.title);
+        let release_id = record.release_id.clone();
+        let review = record.review.clone();
+        let tags = record.tags.clone();
+
+        let change = match existing {
+            Some(existing) if existing.rating == record.rating => None,
+            Some(existing) if !policy.prefers_import(&existing, &record) => None,
+            Some(existing) => {
+                let mut release = Release::from(&existing);
+                release.rating = record.rating;
+
+                database::add_release(&tx, &existing.artist_id, &release)?;
+                Some(Change::Updated {
+                    old: Box::new(existing),
+                    new: record,
+                })
+            }
+            None => {
+                let release = Release {
+                    id: record.release_id.clone(),
+                    title: record.title.clone(),
+                    year: record.year.unwrap_or_default(),
+                    first_release_date: None,
+                    group_type: ReleaseType::parse(&record.release_type),
+                    community: None,
+                    genres: Vec::new(),
+                    community_tags: Vec::new(),
+                    rating: record.rating,
+                    track_average: None,
+                    in_backlog: false,
+                };
+
+                database::add_artist(&tx, &record.artist_id, &record.artist_name)?;
+                database::add_release(&tx, &record.artist_id, &release)?;
+                Some(Change::Added(record))
+            }
+        };
+
+        // Reviews are taken along with the rating, or when there's no review
+        // to overwrite.
+        if let Some(review) = review
+            && (change.is_some() || !has_review)
+        {
+            database::set_review(&tx, &release_id, review.trim())?;
+        }
+
+        let retagged = match tags {
+            Some(tags) if !is_kept => replace_tags(&tx, &release_id, &tags)?,
+            _ => None,
+        };
+
+        match (change, retagged) {
+            (Some(change), _) => {
+                if let Change::Added(record) | Change::Updated { new: record, .. } = &change
+                    && let Some(rated_at) = record.rated_at
+                {
+                    database::set_rated_at(&tx, &record.release_id, rated_at)?;
+                }
+
+                summary.changes.push(change);
+            }
+            (None, Some((old, new))) => {
+                summary.changes.push(Change::Retagged { release, old, new });
+            }
+            (None, None) => summary.unchanged += 1,
+        }
+    }
+
+    if !dry_run {
+        tx.commit()?;
+    }
+
+    Ok(summary)
+}
+
+/// Adds the records to the backlog, or replaces the tags of the releases that
+/// are in it already. Rated releases are left out, since they can't be in the
+/// backlog. Like ratings, a dry run rolls every change back.
+pub fn import_backlog(
+    conn: &Connection,
+    records: Vec<BacklogRecord>,
+    dry_run: bool,
+) -> Result<ImportSummary> {
+    let tx = conn.unchecked_transaction()?;
+    let mut summary = ImportSummary::default();
+
+    for (idx, record) in records.into_iter().enumerate() {
+        if !is_mbid(&record.artist_id) || !is_mbid(&record.release_id) {
+            eprintln!(
+                "Skipping record {}: \"{} - {}\" doesn't have valid identifiers",
+                idx + 1,
+                record.artist_name,
+                record.title
+            );
+            summary.invalid += 1;
+            continue;
+        }
+
+        if database::get_rating(&tx, &record.release_id)?.is_some() {
+            summary.unchanged += 1;
+            continue;
+        }
+
+        let release = Release {
+            id: record.release_id.clone(),
+            title: record.title.clone(),
+            year: record.year.unwrap_or_default(),
+            first_release_date: None,
+            group_type: ReleaseType::parse(&record.release_type),
+            community: None,
+            genres: Vec::new(),
+            community_tags: Vec::new(),
+            rating: None,
+            track_average: None,
+            in_backlog: false,
+        };
+
+        let is_added =
+            database::add_to_backlog(&tx, &record.artist_id, &record.artist_name, &release)?;
+
+        if is_added && let Some(added_at) = record.added_at {
+            database::set_added_at(&tx, &record.release_id, added_at)?;
+        }
+
+        let retagged = match &record.tags {
+            Some(tags) => replace_tags(&tx, &record.release_id, tags)?,
+            None => None,
+        };
+
+        match retagged {
+            _ if is_added => summary.changes.push(Change::Queued(record)),
+            Some((old, new)) => summary.changes.push(Change::Retagged {
+                release: describe(&record.artist_name, record.year, &record.title),
+                old,
+                new,
+            }),
+            None => summary.unchanged += 1,
+        }
+    }
+
+    if !dry_run {
+        tx.commit()?;
+    }
+
+    Ok(summary)
+}
+
+/// Replaces our tags of a release with the imported ones. Records without
+/// tags leave them alone, since exports of older versions don't have any.
+/// Returns the old and the new tags if they differ, the new ones as they
+/// were stored, since tags that are in use keep the case they were first
+/// written in.
+fn replace_tags(
+    conn: &Connection,
+    release_id: &str,
+    tags: &str,
+) -> Result<Option<(Vec<String>, Vec<String>)>> {
+    let old = database::get_tags(conn, release_id)?;
+    let new = parse_tags(tags);
+
+    let normalize = |tags: &[String]| {
+        let mut tags = tags
+            .iter()
+            .map(|tag| tag.to_lowercase())
+            .collect::<Vec<String>>();
+        tags.sort();
+        tags
+    };
+
+    if normalize(&old) == normalize(&new) {
+        return Ok(None);
+    }
+
+    database::set_tags(conn, release_id, &new)?;
+
+    Ok(Some((old, database::get_tags(conn, release_id)?)))
+}
+
+fn is_valid(record: &Record) -> bool {
+    is_mbid(&record.artist_id)
+        && is_mbid(&record.release_id)
+        && record
+            .rating
+            .is_some_and(|rating| (1..=10).contains(&rating))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTIST_ID: &str = "11111111-1111-1111-1111-111111111111";
+    const RELEASE_ID: &str = "22222222-2222-2222-2222-222222222222";
+
+    fn open() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        database::initialize_db(&mut conn).unwrap();
+        conn
+    }
+
+    fn record(rating: u8, rated_at: Option<i64>) -> Record {
+        Record {
+            artist_id: ARTIST_ID.to_string(),
+            artist_name: "Artist".to_string(),
+            release_id: RELEASE_ID.to_string(),
+            title: "Title".to_string(),
+            year: Some(1999),
+            release_type: "Album".to_string(),
+            rating: Some(rating),
+            rated_at,
+            review: None,
+            tags: None,
+        }
+    }
+
+    /// Imports a rating of 6 from 2000 over an existing rating of 8 from 1990,
+    /// returning the resulting rating.
+    fn resolve_conflict(policy: ConflictPolicy) -> u8 {
+        let conn = open();
+        import_ratings(&conn, vec![record(8, Some(631152000))], policy, false).unwrap();
+
+        let summary =
+            import_ratings(&conn, vec![record(6, Some(946684800))], policy, false).unwrap();
+        let rating = database::get_rating(&conn, RELEASE_ID).unwrap().unwrap();
+
+        assert_eq!(summary.changes.len() + summary.unchanged, 1);
+        rating.rating.unwrap()
+    }
+
+    #[test]
+    fn adds_new_ratings_without_inventing_a_release_date() {
+        let conn = open();
+        let summary =
+            import_ratings(&conn, vec![record(7, None)], ConflictPolicy::Keep, false).unwrap();
+        let rating = database::get_rating(&conn, RELEASE_ID).unwrap().unwrap();
+
+        assert!(matches!(summary.changes[..], [Change::Added(_)]));
+        assert_eq!(rating.rating, Some(7));
+        assert_eq!(rating.year, Some(1999));
+        assert_eq!(rating.first_release_date, None);
+        assert!(
+            database::get_releases_missing_metadata(&conn)
+                .unwrap()
+                .contains(&RELEASE_ID.to_string())
+        );
+    }
+
+    #[test]
+    fn keep_policy_keeps_the_existing_rating() {
+        assert_eq!(resolve_conflict(ConflictPolicy::Keep), 8);
+    }
+
+    #[test]
+    fn overwrite_policy_takes_the_imported_rating() {
+        assert_eq!(resolve_conflict(ConflictPolicy::Overwrite), 6);
+    }
+
+    #[test]
+    fn max_policy_takes_the_higher_rating() {
+        assert_eq!(resolve_conflict(ConflictPolicy::Max), 8);
+    }
+
+    #[test]
+    fn newest_policy_takes_the_latest_rating() {
+        assert_eq!(resolve_conflict(ConflictPolicy::Newest), 6);
+    }
+
+    #[test]
+    fn dry_run_changes_nothing() {
+        let conn = open();
+        let summary =
+            import_ratings(&conn, vec![record(7, None)], ConflictPolicy::Keep, true).unwrap();
+
+        assert_eq!(summary.changes.len(), 1);
+        assert!(database::get_rating(&conn, RELEASE_ID).unwrap().is_none());
+    }
+
+    fn tagged(rating: u8, tags: &str) -> Record {
+        Record {
+            tags: Some(tags.to_string()),
+            ..record(rating, None)
+        }
+    }
+
+    fn tags(conn: &Connection) -> Vec<String> {
+        database::get_tags(conn, RELEASE_ID).unwrap()
+    }
+
+    #[test]
+    fn replaces_tags() {
+        let conn = open();
+        import_ratings(
+            &conn,
+            vec![tagged(7, "old, revisit")],
+            ConflictPolicy::Keep,
+            false,
+        )
+        .unwrap();
+
+        let summary = import_ratings(
+            &conn,
+            vec![tagged(7, "Revisit, new")],
+            ConflictPolicy::Keep,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(tags(&conn), ["new", "revisit"]);
+        assert_eq!(summary.unchanged, 0);
+        assert_eq!(
+            summary.changes[0].to_string(),
+            "# Artist - (1999) Title: old, revisit -> new, revisit"
+        );
+    }
+
+    #[test]
+    fn keeps_tags_along_with_the_kept_rating() {
+        let conn = open();
+        import_ratings(&conn, vec![tagged(8, "old")], ConflictPolicy::Keep, false).unwrap();
+
+        let summary =
+            import_ratings(&conn, vec![tagged(6, "new")], ConflictPolicy::Keep, false).unwrap();
+
+        assert_eq!(tags(&conn), ["old"]);
+        assert_eq!(summary.unchanged, 1);
+    }
+
+    #[test]
+    fn takes_tags_along_with_the_imported_rating() {
+        let conn = open();
+        import_ratings(&conn, vec![tagged(8, "old")], ConflictPolicy::Keep, false).unwrap();
+        import_ratings(
+            &conn,
+            vec![tagged(6, "new")],
+            ConflictPolicy::Overwrite,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(tags(&conn), ["new"]);
+    }
+
+    #[test]
+    fn leaves_tags_alone_without_a_tags_column() {
+        let conn = open();
+        import_ratings(&conn, vec![tagged(7, "old")], ConflictPolicy::Keep, false).unwrap();
+
+        let summary =
+            import_ratings(&conn, vec![record(7, None)], ConflictPolicy::Keep, false).unwrap();
+
+        assert_eq!(tags(&conn), ["old"]);
+        assert_eq!(summary.unchanged, 1);
+    }
+
+    #[test]
+    fn round_trips_the_backlog() {
+        let conn = open();
+        let backlog = |tags: &str| {
+            let record = BacklogRecord {
+                artist_id: ARTIST_ID.to_string(),
+                artist_name: "Artist".to_string(),
+                release_id: RELEASE_ID.to_string(),
+                title: "Title".to_string(),
+                year: Some(1999),
+                release_type: "Album".to_string(),
+                added_at: Some(946684800),
+                tags: Some(tags.to_string()),
+            };
+
+            let json = serde_json::to_vec(&[record]).unwrap();
+            read_records::<BacklogRecord>(Format::Json, json.as_slice()).unwrap()
+        };
+
+        let summary = import_backlog(&conn, backlog("revisit"), false).unwrap();
+        let items = database::get_backlog(&conn).unwrap();
+
+        assert!(matches!(summary.changes[..], [Change::Queued(_)]));
+        assert_eq!(items[0].added_at, Some(946684800));
+        assert_eq!(items[0].tags, ["revisit"]);
+
+        let summary = import_backlog(&conn, backlog("later"), false).unwrap();
+
+        assert!(matches!(summary.changes[..], [Change::Retagged { .. }]));
+        assert_eq!(tags(&conn), ["later"]);
+
+        import_ratings(&conn, vec![record(7, None)], ConflictPolicy::Keep, false).unwrap();
+        let summary = import_backlog(&conn, backlog("later"), false).unwrap();
+
+        assert_eq!(summary.unchanged, 1);
+        assert!(database::get_backlog(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn skips_invalid_records() {
+        let conn = open();
+        let mut invalid = record(7, None);
+        invalid.release_id = "not an mbid".to_string();
+
+        let summary = import_ratings(
+            &conn,
+            vec![invalid, record(11, None)],
+            ConflictPolicy::Keep,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(summary.invalid, 2);
+        assert!(summary.changes.is_empty());
+    }
+}
@@ -1,100 +1,373 @@
 mod app;
+mod backlog;
+mod cache;
 mod cli;
+mod commands;
+mod coverart;
 mod database;
+mod export;
+mod import;
 mod musicbrainz;
 mod rating;
+mod rym;
+mod stats;
 mod ui;
 mod utils;
 
-use crate::app::App;
+use crate::app::{App, Message, Tab};
+use crate::cache::Cache;
+use crate::coverart::{CoverFetcher, CoverPainter, CoverSource, Graphics};
 use crate::ui::render;
 use anyhow::Result;
-use crossterm::event::Event;
-use crossterm::event::KeyCode;
+use crossterm::event::{Event, EventStream};
+use crossterm::event::{KeyCode, KeyModifiers};
 use crossterm::execute;
 use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
-use ratatui::backend::{Backend, CrosstermBackend};
+use futures::StreamExt;
 use ratatui::Terminal;
-use std::io;
-use std::io::{stdin, stdout, Write};
+use ratatui::backend::{Backend, CrosstermBackend};
+use std::env;
+use std::io::{self, Stdout};
 use std::panic;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::fs;
+use tokio::process::Command;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let clap_args = cli::get_matches();
 
-    let mut app = if clap_args.get_flag("rated") {
-        App::list_rated()?
-    } else if let Some(query) = clap_args.get_one::<String>("artist") {
-        App::search(query)?
-    } else {
-        print!("Enter artist name: ");
-        stdout().flush()?;
+    if clap_args.get_flag("backfill") {
+        backfill_releases().await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let cache = Cache::open(
+        Duration::from_secs(
+            clap_args
+                .get_one::<u64>("cache_ttl")
+                .unwrap()
+                .saturating_mul(60 * 60),
+        ),
+        clap_args.get_flag("offline"),
+    )?;
 
-        let mut search_query = String::new();
-        stdin().read_line(&mut search_query).unwrap();
-        App::search(&search_query)?
+    if let Some((name, matches)) = clap_args.subcommand() {
+        return commands::run(name, matches, cache).await;
+    }
+
+    let source = match clap_args.get_one::<PathBuf>("covers_from") {
+        Some(dir) => CoverSource::Directory(dir.clone()),
+        None => CoverSource::Archive,
     };
+    let covers = CoverFetcher::new(source, cache.offline)?;
+    let graphics = Graphics::from_name(clap_args.get_one::<String>("graphics").unwrap()).unwrap();
 
-    let default_hook = panic::take_hook();
+    let (tx, rx) = mpsc::unbounded_channel();
 
-    panic::set_hook(Box::new(move |info| {
-        reset_terminal().unwrap();
-        default_hook(info);
-    }));
+    let mut app = App::new(cache, covers, graphics, tx)?;
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
+    if clap_args.get_flag("rated") {
+        app.select_tab(Tab::Ratings);
+    } else if let Some(query) = clap_args.get_one::<String>("artist") {
+        app.search(query.to_string())?;
+    } else {
+        app.open_search_prompt();
+    }
+
+    let mut terminal = init_terminal()?;
 
-    let res = run_tui(&mut terminal, &mut app);
+    let res = run_tui(&mut terminal, &mut app, rx).await;
 
     reset_terminal()?;
 
     if let Err(e) = res {
         eprintln!("{e:?}");
+        return Ok(ExitCode::FAILURE);
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
-fn run_tui<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+async fn run_tui<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut rx: UnboundedReceiver<Message>,
+) -> Result<()> {
+    let mut events = EventStream::new();
+    let mut spinner = tokio::time::interval(Duration::from_millis(100));
+    let mut painter = CoverPainter::new(app.graphics);
+
     loop {
+        app.load_history()?;
+        app.load_cover();
+        app.load_details()?;
         terminal.draw(|f| render(f, app))?;
 
-        if let Event::Key(key) = crossterm::event::read()? {
-            match key.code {
-                KeyCode::Char('q') => break,
-                KeyCode::Char('h') | KeyCode::Left => app.on_left(),
-                KeyCode::Char('l') | KeyCode::Right => app.on_right()?,
-                KeyCode::Char('j') | KeyCode::Down => app.on_down(),
-                KeyCode::Char('k') | KeyCode::Up => app.on_up(),
-                KeyCode::Char(c) => match c {
-                    rating @ '1'..='9' => app.set_rating(rating.to_digit(10).unwrap() as u8),
-                    '0' => app.set_rating(10),
-                    _ => (),
-                },
-                KeyCode::Enter => {
-                    if app.currently_rating {
-                        app.confirm_rating()?
-                    } else if app.releases.is_some() {
-                        app.start_rating();
+        if painter.is_outdated(app.cover_placement.as_ref()) {
+            if painter.needs_clear() {
+                terminal.clear()?;
+                terminal.draw(|f| render(f, app))?;
+            }
+
+            painter.paint(app.placed_cover(), &mut io::stdout())?;
+        }
+
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else {
+                    break;
+                };
+
+                if let Event::Key(key) = event? {
+                    app.status = None;
+
+                    if app.search_prompt.active {
+                        match key.code {
+                            KeyCode::Enter => app.submit_search()?,
+                            KeyCode::Esc => app.close_search_prompt(),
+                            KeyCode::Up => app.search_prompt.previous_query(),
+                            KeyCode::Down => app.search_prompt.next_query(),
+                            KeyCode::Backspace => {
+                                app.search_prompt.query.pop();
+                            }
+                            KeyCode::Char(c) => app.search_prompt.query.push(c),
+                            _ => (),
+                        }
+
+                        continue;
+                    }
+
+                    if let Some(editor) = &mut app.review_editor {
+                        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+                        match key.code {
+                            KeyCode::Char('s') if ctrl => app.save_review()?,
+                            KeyCode::Char('e') if ctrl => {
+                                editor.text = edit_externally(terminal, &editor.text).await?;
+                            }
+                            KeyCode::Esc => app.close_review_editor(),
+                            KeyCode::Enter => editor.text.push('\n'),
+                            KeyCode::Backspace => {
+                                editor.text.pop();
+                            }
+                            KeyCode::Char(c) if !ctrl => editor.text.push(c),
+                            _ => (),
+                        }
+
+                        continue;
+                    }
+
+                    if let Some(editor) = &mut app.tag_editor {
+                        match key.code {
+                            KeyCode::Enter => app.save_tags()?,
+                            KeyCode::Esc => app.close_tag_editor(),
+                            KeyCode::Tab => editor.complete(),
+                            KeyCode::Backspace => {
+                                editor.text.pop();
+                            }
+                            KeyCode::Char(c) => editor.text.push(c),
+                            _ => (),
+                        }
+
+                        continue;
+                    }
+
+                    if let Some(query) = &mut app.find_prompt {
+                        match key.code {
+                            KeyCode::Enter => app.close_find_prompt(),
+                            KeyCode::Esc => app.cancel_find(),
+                            KeyCode::Backspace => {
+                                query.pop();
+                                app.update_find_query();
+                            }
+                            KeyCode::Char(c) => {
+                                query.push(c);
+                                app.update_find_query();
+                            }
+                            _ => (),
+                        }
+
+                        continue;
+                    }
+
+                    if let Some(query) = &mut app.filter_prompt {
+                        match key.code {
+                            KeyCode::Enter => app.submit_filter()?,
+                            KeyCode::Esc => app.close_filter_prompt(),
+                            KeyCode::Backspace => {
+                                query.pop();
+                            }
+                            KeyCode::Char(c) => query.push(c),
+                            _ => (),
+                        }
+
+                        continue;
+                    }
+
+                    if app.pending_unrate.is_some() {
+                        match key.code {
+                            KeyCode::Char('y') => app.confirm_unrate()?,
+                            _ => app.cancel_unrate(),
+                        }
+
+                        continue;
+                    }
+
+                    match key.code {
+                        KeyCode::Char('q') => break,
+                        KeyCode::Char('h') | KeyCode::Left => app.on_left(),
+                        KeyCode::Char('l') | KeyCode::Right => app.on_right()?,
+                        KeyCode::Char('j') | KeyCode::Down => app.on_down(),
+                        KeyCode::Char('k') | KeyCode::Up => app.on_up(),
+                        KeyCode::Char(c) => match c {
+                            rating @ '1'..='9' => {
+                                app.set_rating(rating.to_digit(10).unwrap() as u8)
+                            }
+                            '0' => app.set_rating(10),
+                            's' => app.open_search_prompt(),
+                            'd' | 'x' => app.start_unrate(),
+                            'u' => app.undo_rating()?,
+                            'r' => app.open_review_editor()?,
+                            't' => app.open_tag_editor()?,
+                            'b' => app.toggle_backlog()?,
+                            'o' => app.cycle_sort()?,
+                            'O' => app.toggle_sort_direction()?,
+                            'f' => app.open_filter_prompt(),
+                            '/' => app.open_find_prompt(),
+                            'H' => app.toggle_history(),
+                            '[' => app.previous_tab(),
+                            ']' => app.next_tab(),
+                            _ => (),
+                        },
+                        KeyCode::Tab => app.toggle_details(),
+                        KeyCode::Enter => {
+                            if app.currently_rating {
+                                app.confirm_rating()?
+                            } else {
+                                app.start_rating();
+                            }
+                        }
+                        KeyCode::Esc => {
+                            if app.currently_rating {
+                                app.abort_rating();
+                            } else {
+                                app.cancel_fetch();
+                            }
+                        }
+                        _ => (),
                     }
                 }
-                KeyCode::Esc => app.abort_rating(),
-                _ => (),
             }
+            Some(message) = rx.recv() => app.handle_message(message)?,
+            _ = spinner.tick(), if app.is_loading() => app.tick(),
         }
     }
 
     Ok(())
 }
 
+async fn backfill_releases() -> Result<()> {
+    let conn = database::open_db()?;
+    let release_ids = database::get_releases_missing_metadata(&conn)?;
+    let mut updated = 0;
+
+    for release_id in &release_ids {
+        match musicbrainz::fetch_release(release_id).await {
+            Ok((release, _)) => {
+                database::update_release_metadata(&conn, &release)?;
+                updated += 1;
+            }
+            Err(e) => eprintln!("Couldn't fetch {release_id}: {e}"),
+        }
+    }
+
+    println!("Updated {updated} of {} releases.", release_ids.len());
+
+    // Genres of artists are kept apart from the ones of their release groups,
+    // so they're fetched once per artist.
+    let artist_ids = database::get_artists_missing_genres(&conn)?;
+    let mut updated = 0;
+
+    for artist_id in &artist_ids {
+        match musicbrainz::fetch_artist_genres(artist_id).await {
+            Ok(genres) => {
+                database::update_artist_genres(&conn, artist_id, &genres)?;
+                updated += 1;
+            }
+            Err(e) => eprintln!("Couldn't fetch {artist_id}: {e}"),
+        }
+    }
+
+    println!(
+        "Updated the genres of {updated} of {} artists.",
+        artist_ids.len()
+    );
+
+    Ok(())
+}
+
+/// Lets the user edit the text in `$VISUAL` or `$EDITOR`, suspending the TUI
+/// until the editor exits. Background tasks keep running in the meantime.
+async fn edit_externally<B: Backend>(terminal: &mut Terminal<B>, text: &str) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // The file gets a random name and is created only if nothing is at its
+    // path yet, so that nobody else sharing the temporary directory can plant
+    // it in advance.
+    let file = tempfile::Builder::new()
+        .prefix("rambt-review-")
+        .suffix(".md")
+        .tempfile()?;
+    let path = file.path();
+    fs::write(path, text).await?;
+
+    reset_terminal()?;
+
+    let mut args = editor.split_whitespace();
+    let status = Command::new(args.next().unwrap_or("vi"))
+        .args(args)
+        .arg(path)
+        .status()
+        .await;
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    terminal.clear()?;
+
+    let edited = match status {
+        Ok(status) if status.success() => fs::read_to_string(path).await?,
+        _ => text.to_string(),
+    };
+    file.close()?;
+
+    Ok(edited)
+}
+
+fn init_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        reset_terminal().unwrap();
+        default_hook(info);
+    }));
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    terminal.clear()?;
+
+    Ok(terminal)
+}
+
 fn reset_terminal() -> Result<()> {
     disable_raw_mode()?;
     execute!(io::stdout(), LeaveAlternateScreen)?;
@@ -1,14 +1,21 @@
-use crate::{app::ReleaseType, rating::Rate};
+use crate::{app::ReleaseType, rating::Rate, ui::Searchable};
 use musicbrainz_rs::{
+    BrowseQuery,
     chrono::Datelike,
     entity::{
         artist::{Artist, ArtistSearchQuery},
-        release_group::ReleaseGroup,
+        genre::Genre,
+        rating::Rating,
+        release::{Release as MbRelease, ReleaseStatus},
+        release_group::{ReleaseGroup, ReleaseGroupSearchQuery},
+        tag::Tag,
     },
     prelude::*,
 };
-use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt::Display};
 
+#[derive(Serialize, Deserialize)]
 pub struct ArtistSearchResult {
     pub id: String,
     pub name: String,
@@ -21,6 +28,12 @@ impl Display for ArtistSearchResult {
     }
 }
 
+impl Searchable for ArtistSearchResult {
+    fn search_text(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl From<&Artist> for ArtistSearchResult {
     fn from(value: &Artist) -> Self {
         ArtistSearchResult {
@@ -31,13 +44,70 @@ impl From<&Artist> for ArtistSearchResult {
     }
 }
 
-#[derive(Default)]
+/// The average rating MusicBrainz users gave a release group.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct CommunityRating {
+    /// Out of 10 like our ratings, even though MusicBrainz rates out of 5.
+    pub value: f32,
+    pub votes: u32,
+}
+
+impl CommunityRating {
+    /// `None` if nobody has rated the release group yet.
+    fn new(rating: Option<Rating>) -> Option<Self> {
+        let rating = rating?;
+
+        Some(Self {
+            value: rating.value? * 2.0,
+            votes: rating.vote_count.unwrap_or_default(),
+        })
+    }
+
+    /// How much higher we rated the release group than the community did.
+    pub fn difference(&self, rating: u8) -> f32 {
+        rating as f32 - self.value
+    }
+}
+
+impl Display for CommunityRating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1} ({} votes)", self.value, self.votes)
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Release {
     pub id: String,
     pub title: String,
     pub year: i32,
+    pub first_release_date: Option<String>,
     pub group_type: ReleaseType,
+    /// Only fetched along with discographies, so releases from searches don't
+    /// have one.
+    #[serde(default)]
+    pub community: Option<CommunityRating>,
+    /// Genres of the release group, the most voted first.
+    #[serde(default)]
+    pub genres: Vec<String>,
+    /// Tags of the release group on MusicBrainz that aren't genres, the most
+    /// voted first.
+    #[serde(default)]
+    pub community_tags: Vec<String>,
+    #[serde(skip)]
     pub rating: Option<u8>,
+    /// Average rating of the rated tracks.
+    #[serde(skip)]
+    pub track_average: Option<f64>,
+    #[serde(skip)]
+    pub in_backlog: bool,
+}
+
+impl Release {
+    /// The release year, if it's known. Release groups without a first
+    /// release date on MusicBrainz are left with a year of 0.
+    pub fn known_year(&self) -> Option<i32> {
+        (self.year != 0).then_some(self.year)
+    }
 }
 
 impl Rate for Release {
@@ -48,26 +118,103 @@ impl Rate for Release {
 
 impl Display for Release {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}) {}", self.year, self.title)
+        match self.known_year() {
+            Some(year) => write!(f, "({}) {}", year, self.title),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
+impl Searchable for Release {
+    fn search_text(&self) -> String {
+        self.to_string()
     }
 }
 
 impl From<ReleaseGroup> for Release {
     fn from(value: ReleaseGroup) -> Self {
+        let genres = genre_names(value.genres);
+        let community_tags = tag_names(value.tags, &genres);
+        let first_release_date = value.first_release_date.filter(|date| !date.0.is_empty());
+
         Release {
             id: value.id.to_string(),
             title: value.title.to_string(),
-            year: value
-                .first_release_date
+            year: first_release_date
+                .as_ref()
                 .and_then(|date| date.into_naive_date(1, 1, 1).ok())
                 .unwrap_or_default()
                 .year(),
+            first_release_date: first_release_date.map(String::from),
             group_type: ReleaseType::new(value.primary_type, value.secondary_types),
+            community: CommunityRating::new(value.rating),
+            genres,
+            community_tags,
             rating: None,
+            track_average: None,
+            in_backlog: false,
         }
     }
 }
 
+fn genre_names(genres: Option<Vec<Genre>>) -> Vec<String> {
+    let mut genres = genres.unwrap_or_default();
+    genres.sort_by_key(|genre| std::cmp::Reverse(genre.count));
+
+    genres.into_iter().map(|genre| genre.name).collect()
+}
+
+/// Names of the tags that aren't genres. Genres are tags on MusicBrainz as
+/// well.
+fn tag_names(tags: Option<Vec<Tag>>, genres: &[String]) -> Vec<String> {
+    let mut tags = tags.unwrap_or_default();
+    tags.sort_by_key(|tag| std::cmp::Reverse(tag.count));
+
+    tags.into_iter()
+        .map(|tag| tag.name)
+        .filter(|tag| !genres.contains(tag))
+        .collect()
+}
+
+/// A track of the release that represents a release group.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Track {
+    /// MBID of the recording, which unlike the track is the same on every
+    /// release of the release group.
+    pub id: String,
+    pub title: String,
+    pub number: String,
+    /// Length of the track in milliseconds.
+    pub length: Option<u32>,
+    #[serde(skip)]
+    pub rating: Option<u8>,
+}
+
+impl Rate for Track {
+    fn rating(&mut self) -> &mut Option<u8> {
+        &mut self.rating
+    }
+}
+
+impl Display for Track {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}. {}", self.number, self.title)?;
+
+        if let Some(length) = self.length {
+            let seconds = length / 1000;
+            write!(f, " ({}:{:02})", seconds / 60, seconds % 60)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Searchable for Track {
+    fn search_text(&self) -> String {
+        self.to_string()
+    }
+}
+
 pub async fn search_artist(artist_name: &str) -> Result<Vec<ArtistSearchResult>, Error> {
     let query = ArtistSearchQuery::query_builder()
         .artist(artist_name)
@@ -82,17 +229,234 @@ pub async fn search_artist(artist_name: &str) -> Result<Vec<ArtistSearchResult>,
         .collect::<Vec<ArtistSearchResult>>())
 }
 
-pub async fn fetch_releases(artist_id: &str) -> Result<Option<Vec<Release>>, Error> {
+/// Searches the release groups of an artist by their title.
+pub async fn search_release_groups(artist_id: &str, title: &str) -> Result<Vec<Release>, Error> {
+    let query = ReleaseGroupSearchQuery::query_builder()
+        .release_group(title)
+        .and()
+        .arid(artist_id)
+        .build();
+
+    let query_result = ReleaseGroup::search(query).execute().await?;
+
+    Ok(query_result
+        .entities
+        .into_iter()
+        .map(Release::from)
+        .collect::<Vec<Release>>())
+}
+
+/// The maximum number of release groups MusicBrainz returns per browse request.
+const RELEASE_GROUPS_PAGE_SIZE: u8 = 100;
+
+pub struct ReleasesPage {
+    pub releases: Vec<Release>,
+    /// The total number of release groups credited to the artist.
+    pub count: usize,
+}
+
+/// Asks a browse query for ratings. Release group browse queries in
+/// musicbrainz_rs 0.12 have builders for genres and tags but not for ratings.
+/// `BrowseQuery::create_url` appends the `id` of the browsed entity to the URL
+/// verbatim, after the includes of the builders, so the ratings include is
+/// appended to it as a parameter of its own. This has to be checked again
+/// whenever musicbrainz_rs is upgraded.
+fn add_browse_ratings<T>(query: &mut BrowseQuery<T>) {
+    query.id.push_str("&inc=ratings");
+}
+
+pub async fn fetch_releases_page(artist_id: &str, offset: usize) -> Result<ReleasesPage, Error> {
+    let mut query = ReleaseGroup::browse();
+    query.by_artist(artist_id).with_genres().with_tags();
+    add_browse_ratings(&mut query);
+
+    let result = query
+        .limit(RELEASE_GROUPS_PAGE_SIZE)
+        .offset(offset as u16)
+        .execute()
+        .await?;
+
+    Ok(ReleasesPage {
+        releases: result.entities.into_iter().map(Release::from).collect(),
+        count: result.count as usize,
+    })
+}
+
+pub async fn fetch_releases(artist_id: &str) -> Result<Vec<Release>, Error> {
+    let mut releases = Vec::new();
+
+    loop {
+        let page = fetch_releases_page(artist_id, releases.len()).await?;
+        let is_last_page = page.releases.is_empty();
+
+        releases.extend(page.releases);
+
+        if is_last_page || releases.len() >= page.count {
+            break;
+        }
+    }
+
+    Ok(releases)
+}
+
+/// Fetches a release group along with the first artist it is credited to.
+pub async fn fetch_release(
+    release_id: &str,
+) -> Result<(Release, Option<ArtistSearchResult>), Error> {
+    let release_group = ReleaseGroup::fetch()
+        .id(release_id)
+        .with_artists()
+        .with_ratings()
+        .with_genres()
+        .with_tags()
+        .execute()
+        .await?;
+
+    let artist = release_group
+        .artist_credit
+        .as_ref()
+        .and_then(|credits| credits.first())
+        .map(|credit| ArtistSearchResult::from(&credit.artist));
+
+    Ok((Release::from(release_group), artist))
+}
+
+pub async fn fetch_artist_genres(artist_id: &str) -> Result<Vec<String>, Error> {
     let artist = Artist::fetch()
-        .with_release_groups()
         .id(artist_id)
+        .with_genres()
+        .execute()
+        .await?;
+
+    Ok(genre_names(artist.genres))
+}
+
+/// Fetches the tracklist of a release group. Release groups don't have
+/// tracks of their own, so the tracks of the earliest official release are
+/// used.
+pub async fn fetch_tracks(release_group_id: &str) -> Result<Vec<Track>, Error> {
+    let release_group = ReleaseGroup::fetch()
+        .id(release_group_id)
+        .with_releases()
         .execute()
         .await?;
 
-    Ok(artist.release_groups.map(|release_groups| {
-        release_groups
-            .into_iter()
-            .map(Release::from)
-            .collect::<Vec<Release>>()
-    }))
+    let representative = release_group
+        .releases
+        .unwrap_or_default()
+        .into_iter()
+        .min_by_key(|release| {
+            let date = release
+                .date
+                .as_ref()
+                .map(|date| date.0.clone())
+                .unwrap_or_default();
+            (
+                release.status != Some(ReleaseStatus::Official),
+                date.is_empty(),
+                date,
+            )
+        });
+
+    let Some(representative) = representative else {
+        return Ok(Vec::new());
+    };
+
+    let release = MbRelease::fetch()
+        .id(&representative.id)
+        .with_recordings()
+        .execute()
+        .await?;
+
+    Ok(release
+        .media
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|media| media.tracks.unwrap_or_default())
+        .map(|track| Track {
+            id: track.recording.map_or(track.id, |recording| recording.id),
+            title: track.title,
+            number: track.number,
+            length: track.length,
+            rating: None,
+        })
+        .collect())
+}
+
+/// What MusicBrainz knows about a release group beyond what discographies
+/// list.
+#[derive(Serialize, Deserialize)]
+pub struct ReleaseDetails {
+    /// The number of releases in the release group.
+    pub release_count: usize,
+    /// The number of differently titled or disambiguated releases, such as
+    /// deluxe editions and remasters.
+    pub editions: usize,
+    pub community: Option<CommunityRating>,
+    pub genres: Vec<String>,
+    /// Tags that aren't genres.
+    pub tags: Vec<String>,
+}
+
+pub async fn fetch_release_details(release_group_id: &str) -> Result<ReleaseDetails, Error> {
+    let release_group = ReleaseGroup::fetch()
+        .id(release_group_id)
+        .with_ratings()
+        .with_genres()
+        .with_tags()
+        .execute()
+        .await?;
+
+    // Only the first page of releases is looked at for editions, which is
+    // enough for all but the most reissued release groups.
+    let releases = MbRelease::browse()
+        .by_release_group(release_group_id)
+        .limit(RELEASE_GROUPS_PAGE_SIZE)
+        .execute()
+        .await?;
+
+    let editions = releases
+        .entities
+        .iter()
+        .map(|release| {
+            (
+                release.title.as_str(),
+                release.disambiguation.as_deref().unwrap_or_default(),
+            )
+        })
+        .collect::<HashSet<(&str, &str)>>()
+        .len();
+
+    let genres = genre_names(release_group.genres);
+
+    Ok(ReleaseDetails {
+        release_count: releases.count as usize,
+        editions,
+        community: CommunityRating::new(release_group.rating),
+        tags: tag_names(release_group.tags, &genres),
+        genres,
+    })
+}
+
+pub async fn fetch_artist(artist_id: &str) -> Result<ArtistSearchResult, Error> {
+    let artist = Artist::fetch().id(artist_id).execute().await?;
+
+    Ok(ArtistSearchResult::from(&artist))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_ratings_to_the_browsed_entity() {
+        let mut query = ReleaseGroup::browse();
+        query.by_artist("11111111-1111-1111-1111-111111111111");
+        add_browse_ratings(&mut query);
+
+        assert_eq!(
+            query.id,
+            "artist=11111111-1111-1111-1111-111111111111&inc=ratings"
+        );
+    }
 }
@@ -1,3 +1,9 @@
+use crate::{
+    app::ReleaseType,
+    musicbrainz::{CommunityRating, Release},
+    ui::Searchable,
+    utils::format_timestamp,
+};
 use std::fmt::Display;
 
 pub trait Rate {
@@ -8,18 +14,18 @@ pub trait Rate {
     }
 
     fn increase_rating(&mut self) {
-        if let Some(rating) = self.rating() {
-            if *rating != 10 {
-                *rating += 1;
-            }
+        if let Some(rating) = self.rating()
+            && *rating != 10
+        {
+            *rating += 1;
         }
     }
 
     fn decrease_rating(&mut self) {
-        if let Some(rating) = self.rating() {
-            if *rating != 1 {
-                *rating -= 1;
-            }
+        if let Some(rating) = self.rating()
+            && *rating != 1
+        {
+            *rating -= 1;
         }
     }
 }
@@ -30,6 +36,41 @@ pub struct Rated {
     pub release_id: String,
     pub title: String,
     pub rating: Option<u8>,
+    pub year: Option<i32>,
+    pub group_type: ReleaseType,
+    pub first_release_date: Option<String>,
+    /// Unix timestamp of the last time the rating changed.
+    pub rated_at: Option<i64>,
+    pub review: Option<String>,
+    /// Average rating of the rated tracks.
+    pub track_average: Option<f64>,
+    pub community: Option<CommunityRating>,
+    /// Genres of the release group.
+    pub genres: Vec<String>,
+    /// Genres of the artist, kept apart from the ones of the release group.
+    pub artist_genres: Vec<String>,
+    /// Tags of the release group on MusicBrainz that aren't genres.
+    pub community_tags: Vec<String>,
+    /// Our own tags.
+    pub tags: Vec<String>,
+}
+
+impl Rated {
+    /// The genres of the release group, or of its artist if nobody gave the
+    /// release group any. These are what the ratings are filtered and
+    /// counted by.
+    pub fn effective_genres(&self) -> &[String] {
+        if self.genres.is_empty() {
+            &self.artist_genres
+        } else {
+            &self.genres
+        }
+    }
+
+    /// How far our rating is from the community rating, in either direction.
+    pub fn disagreement(&self) -> Option<f32> {
+        Some(self.community?.difference(self.rating?).abs())
+    }
 }
 
 impl Rate for Rated {
@@ -48,12 +89,451 @@ impl TryFrom<&rusqlite::Row<'_>> for Rated {
             release_id: row.get(2)?,
             title: row.get(3)?,
             rating: row.get(4)?,
+            year: row.get(5)?,
+            group_type: ReleaseType::from_columns(row.get(6)?, row.get(7)?),
+            first_release_date: row.get(8)?,
+            rated_at: row.get(9)?,
+            review: row.get(10)?,
+            track_average: row.get(11)?,
+            community: match row.get(12)? {
+                Some(value) => Some(CommunityRating {
+                    value,
+                    votes: row.get::<_, Option<u32>>(13)?.unwrap_or_default(),
+                }),
+                None => None,
+            },
+            genres: Vec::new(),
+            artist_genres: Vec::new(),
+            community_tags: Vec::new(),
+            tags: Vec::new(),
+        })
+    }
+}
+
+/// A change to the rating of a release. A missing old rating means the release
+/// was rated for the first time, a missing new one that the rating was removed.
+pub struct RatingEvent {
+    pub release_id: String,
+    pub artist_name: Option<String>,
+    pub title: Option<String>,
+    pub old_rating: Option<u8>,
+    pub new_rating: Option<u8>,
+    /// Unix timestamp of the change.
+    pub changed_at: Option<i64>,
+}
+
+impl TryFrom<&rusqlite::Row<'_>> for RatingEvent {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row) -> Result<Self, Self::Error> {
+        Ok(RatingEvent {
+            release_id: row.get(0)?,
+            artist_name: row.get(1)?,
+            title: row.get(2)?,
+            old_rating: row.get(3)?,
+            new_rating: row.get(4)?,
+            changed_at: row.get(5)?,
         })
     }
 }
 
+impl RatingEvent {
+    /// The change itself, such as `6 -> 8`.
+    pub fn change(&self) -> String {
+        let format_rating = |rating: Option<u8>| match rating {
+            Some(rating) => rating.to_string(),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{} -> {}",
+            format_rating(self.old_rating),
+            format_rating(self.new_rating)
+        )
+    }
+
+    pub fn date(&self) -> String {
+        self.changed_at
+            .map(format_timestamp)
+            .unwrap_or_else(|| "unknown date".to_string())
+    }
+}
+
+/// Criteria for narrowing down the rated releases.
+#[derive(Default)]
+pub struct Filter {
+    pub min_rating: Option<u8>,
+    pub max_rating: Option<u8>,
+    pub artist: Option<String>,
+    /// First year of the decade, such as 1990.
+    pub decade: Option<i32>,
+    pub release_type: Option<String>,
+    pub genre: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl Filter {
+    /// Parses filters written as `min:7 max:9 artist:name decade:1990
+    /// type:album genre:hip_hop tag:road_trip`. Words without a prefix are
+    /// matched against the artist, and underscores in genres and tags stand
+    /// for spaces.
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut filter = Filter::default();
+        let mut artist = Vec::new();
+
+        for word in query.split_whitespace() {
+            let Some((key, value)) = word.split_once(':') else {
+                artist.push(word);
+                continue;
+            };
+
+            match key {
+                "min" => filter.min_rating = Some(parse_rating(value)?),
+                "max" => filter.max_rating = Some(parse_rating(value)?),
+                "artist" => artist.push(value),
+                "decade" => filter.decade = Some(parse_decade(value)?),
+                "type" => filter.release_type = Some(value.to_string()),
+                "genre" => filter.genre = Some(value.replace('_', " ")),
+                "tag" => filter.tag = Some(value.replace('_', " ")),
+                _ => return Err(format!("Unknown filter \"{key}\"")),
+            }
+        }
+
+        if !artist.is_empty() {
+            filter.artist = Some(artist.join(" "));
+        }
+
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_string().is_empty()
+    }
+
+    pub fn matches(&self, item: &Rated) -> bool {
+        let rating = item.rating.unwrap_or_default();
+
+        self.min_rating.is_none_or(|min| rating >= min)
+            && self.max_rating.is_none_or(|max| rating <= max)
+            && self.artist.as_ref().is_none_or(|artist| {
+                item.artist_name
+                    .to_lowercase()
+                    .contains(&artist.to_lowercase())
+            })
+            && self.decade.is_none_or(|decade| {
+                item.year
+                    .is_some_and(|year| year - year.rem_euclid(10) == decade)
+            })
+            && self
+                .release_type
+                .as_ref()
+                .is_none_or(|release_type| item.group_type.has_type(release_type))
+            && self.genre.as_ref().is_none_or(|genre| {
+                item.effective_genres()
+                    .iter()
+                    .any(|other| other.eq_ignore_ascii_case(genre))
+            })
+            && self.tag.as_ref().is_none_or(|tag| {
+                item.tags
+                    .iter()
+                    .any(|other| other.eq_ignore_ascii_case(tag))
+            })
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+
+        if let Some(min) = self.min_rating {
+            parts.push(format!("min:{min}"));
+        }
+
+        if let Some(max) = self.max_rating {
+            parts.push(format!("max:{max}"));
+        }
+
+        if let Some(artist) = &self.artist {
+            parts.push(artist.clone());
+        }
+
+        if let Some(decade) = self.decade {
+            parts.push(format!("decade:{decade}"));
+        }
+
+        if let Some(release_type) = &self.release_type {
+            parts.push(format!("type:{release_type}"));
+        }
+
+        if let Some(genre) = &self.genre {
+            parts.push(format!("genre:{}", genre.replace(' ', "_")));
+        }
+
+        if let Some(tag) = &self.tag {
+            parts.push(format!("tag:{}", tag.replace(' ', "_")));
+        }
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+fn parse_rating(value: &str) -> Result<u8, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|rating| (1..=10).contains(rating))
+        .ok_or_else(|| format!("\"{value}\" is not a rating from 1 to 10"))
+}
+
+/// Parses decades written as `1990` or `1990s`, up to the 9990s.
+pub fn parse_decade(value: &str) -> Result<i32, String> {
+    value
+        .trim_end_matches('s')
+        .parse::<i32>()
+        .ok()
+        .filter(|year| (0..10000).contains(year) && year % 10 == 0)
+        .ok_or_else(|| format!("\"{value}\" is not a decade such as 1990"))
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortField {
+    #[default]
+    Rating,
+    Artist,
+    Title,
+    Year,
+    RatedAt,
+    /// How far our rating is from the community rating.
+    Disagreement,
+}
+
+impl SortField {
+    fn name(self) -> &'static str {
+        match self {
+            SortField::Rating => "rating",
+            SortField::Artist => "artist",
+            SortField::Title => "title",
+            SortField::Year => "year",
+            SortField::RatedAt => "date rated",
+            SortField::Disagreement => "disagreement",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            SortField::Rating => SortField::Artist,
+            SortField::Artist => SortField::Title,
+            SortField::Title => SortField::Year,
+            SortField::Year => SortField::RatedAt,
+            SortField::RatedAt => SortField::Disagreement,
+            SortField::Disagreement => SortField::Rating,
+        }
+    }
+}
+
+/// Order of the rated list. Ratings start out from the highest, the way they
+/// were always listed.
+pub struct RatingSort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Default for RatingSort {
+    fn default() -> Self {
+        Self {
+            field: SortField::Rating,
+            descending: true,
+        }
+    }
+}
+
+impl RatingSort {
+    /// Moves on to the next field, in the direction that suits it the most.
+    pub fn next_field(&mut self) {
+        self.field = self.field.next();
+        self.descending = matches!(
+            self.field,
+            SortField::Rating | SortField::RatedAt | SortField::Disagreement
+        );
+    }
+
+    pub fn toggle_direction(&mut self) {
+        self.descending = !self.descending;
+    }
+
+    pub fn sort(&self, items: &mut [Rated]) {
+        match self.field {
+            SortField::Rating => items.sort_by_key(|item| item.rating),
+            SortField::Artist => items.sort_by_cached_key(|item| {
+                (
+                    item.artist_name.to_lowercase(),
+                    item.first_release_date.clone(),
+                )
+            }),
+            SortField::Title => items.sort_by_cached_key(|item| item.title.to_lowercase()),
+            SortField::Year => items.sort_by_key(|item| item.first_release_date.clone()),
+            SortField::RatedAt => items.sort_by_key(|item| item.rated_at),
+            // Releases without a community rating are treated as agreeing
+            // with it the most.
+            SortField::Disagreement => items.sort_by(|a, b| {
+                a.disagreement()
+                    .unwrap_or(-1.0)
+                    .total_cmp(&b.disagreement().unwrap_or(-1.0))
+            }),
+        }
+
+        if self.descending {
+            items.reverse();
+        }
+    }
+}
+
+impl Display for RatingSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.field.name(),
+            if self.descending { "↓" } else { "↑" }
+        )
+    }
+}
+
+impl From<&Rated> for Release {
+    fn from(value: &Rated) -> Self {
+        Release {
+            id: value.release_id.clone(),
+            title: value.title.clone(),
+            year: value.year.unwrap_or_default(),
+            first_release_date: value.first_release_date.clone(),
+            group_type: value.group_type.clone(),
+            community: value.community,
+            genres: value.genres.clone(),
+            community_tags: value.community_tags.clone(),
+            rating: value.rating,
+            track_average: value.track_average,
+            in_backlog: false,
+        }
+    }
+}
+
 impl Display for Rated {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} - {}", self.artist_name, self.title)
+        match self.year {
+            Some(year) => write!(f, "{} - ({}) {}", self.artist_name, year, self.title),
+            None => write!(f, "{} - {}", self.artist_name, self.title),
+        }
+    }
+}
+
+impl Searchable for Rated {
+    fn search_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub fn rated(artist_name: &str, title: &str, rating: u8, year: Option<i32>) -> Rated {
+        Rated {
+            artist_id: format!("{artist_name}-id"),
+            artist_name: artist_name.to_string(),
+            release_id: format!("{artist_name}-{title}-id"),
+            title: title.to_string(),
+            rating: Some(rating),
+            year,
+            group_type: ReleaseType::parse("Album"),
+            first_release_date: year.map(|year| format!("{year}-01-01")),
+            rated_at: None,
+            review: None,
+            track_average: None,
+            community: None,
+            genres: Vec::new(),
+            artist_genres: Vec::new(),
+            community_tags: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_decades() {
+        assert_eq!(parse_decade("1990"), Ok(1990));
+        assert_eq!(parse_decade("1990s"), Ok(1990));
+        assert!(parse_decade("1995").is_err());
+        assert!(parse_decade("nineties").is_err());
+        assert!(parse_decade("-10").is_err());
+        assert!(parse_decade("2147483640").is_err());
+    }
+
+    #[test]
+    fn parses_filters() {
+        let filter = Filter::parse(
+            "min:7 max:9 the beatles decade:1960s type:Live genre:hip_hop tag:road_trip",
+        )
+        .unwrap();
+
+        assert_eq!(filter.min_rating, Some(7));
+        assert_eq!(filter.max_rating, Some(9));
+        assert_eq!(filter.artist.as_deref(), Some("the beatles"));
+        assert_eq!(filter.decade, Some(1960));
+        assert_eq!(filter.release_type.as_deref(), Some("Live"));
+        assert_eq!(filter.genre.as_deref(), Some("hip hop"));
+        assert_eq!(filter.tag.as_deref(), Some("road trip"));
+        assert_eq!(
+            filter.to_string(),
+            "min:7 max:9 the beatles decade:1960 type:Live genre:hip_hop tag:road_trip"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        assert!(Filter::parse("min:11").is_err());
+        assert!(Filter::parse("decade:1995").is_err());
+        assert!(Filter::parse("label:warp").is_err());
+        assert!(Filter::parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn matches_ratings() {
+        let mut item = rated("The Beatles", "Revolver", 9, Some(1966));
+        item.genres = vec!["Rock".to_string()];
+        item.tags = vec!["road trip".to_string()];
+
+        let matches = |query: &str| Filter::parse(query).unwrap().matches(&item);
+
+        assert!(matches(""));
+        assert!(matches("beatles min:9 max:10"));
+        assert!(matches("decade:1960 type:album genre:rock tag:Road_Trip"));
+        assert!(!matches("min:10"));
+        assert!(!matches("max:8"));
+        assert!(!matches("stones"));
+        assert!(!matches("decade:1970"));
+        assert!(!matches("type:live"));
+        assert!(!matches("genre:jazz"));
+        assert!(!matches("tag:revisit"));
+    }
+
+    #[test]
+    fn matches_artist_genres_only_without_release_genres() {
+        let mut item = rated("Miles Davis", "Bitches Brew", 9, Some(1970));
+        item.artist_genres = vec!["jazz".to_string()];
+
+        let filter = Filter::parse("genre:jazz").unwrap();
+        assert!(filter.matches(&item));
+
+        item.genres = vec!["jazz fusion".to_string()];
+        assert!(!filter.matches(&item));
+    }
+
+    #[test]
+    fn matches_decades_by_their_years() {
+        let filter = Filter::parse("decade:1990").unwrap();
+
+        assert!(filter.matches(&rated("Artist", "First", 5, Some(1990))));
+        assert!(filter.matches(&rated("Artist", "Last", 5, Some(1999))));
+        assert!(!filter.matches(&rated("Artist", "Next", 5, Some(2000))));
+        assert!(!filter.matches(&rated("Artist", "Undated", 5, None)));
     }
 }
@@ -0,0 +1,401 @@
+use crate::{
+    database,
+    musicbrainz::{self, ArtistSearchResult, Release},
+    ui::{self, StatefulList},
+};
+use anyhow::{Context, Result, bail};
+use crossterm::event::{Event, EventStream, KeyCode};
+use futures::StreamExt;
+use musicbrainz_rs::Error;
+use ratatui::{Terminal, backend::Backend};
+use rusqlite::Connection;
+use std::{fmt::Display, io::Read};
+
+/// A rating from a RateYourMusic export.
+#[derive(Clone)]
+pub struct RymRow {
+    pub artist: String,
+    pub title: String,
+    pub year: Option<i32>,
+    pub rating: u8,
+}
+
+impl Display for RymRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{} - ({}) {}", self.artist, year, self.title),
+            None => write!(f, "{} - {}", self.artist, self.title),
+        }
+    }
+}
+
+/// Column indices of the fields we care about. RYM has changed the layout of
+/// its exports over the years, so the columns are looked up by their headers.
+struct Columns {
+    artist: Vec<usize>,
+    title: usize,
+    date: Option<usize>,
+    rating: usize,
+}
+
+impl Columns {
+    fn from_headers(headers: &csv::StringRecord) -> Result<Self> {
+        let find = |names: &[&str]| {
+            headers
+                .iter()
+                .position(|header| names.contains(&normalize_header(header).as_str()))
+        };
+
+        // Older exports have a single artist column, newer ones split the name
+        // of the artist into a first and a last name.
+        let artist = match find(&["artist", "artistname"]) {
+            Some(idx) => vec![idx],
+            None => [find(&["firstname"]), find(&["lastname"])]
+                .into_iter()
+                .flatten()
+                .collect(),
+        };
+
+        let (Some(title), Some(rating)) = (find(&["title", "album", "release"]), find(&["rating"]))
+        else {
+            bail!("The file doesn't look like a RateYourMusic export");
+        };
+
+        if artist.is_empty() {
+            bail!("The file doesn't have an artist column");
+        }
+
+        Ok(Self {
+            artist,
+            title,
+            date: find(&["releasedate", "date", "year"]),
+            rating,
+        })
+    }
+}
+
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Converts a rating on RYM's 0.5 to 5 star scale to a rating from 1 to 10.
+fn convert_rating(value: &str) -> Option<u8> {
+    let stars = value.trim().parse::<f32>().ok()?;
+
+    (0.5..=5.0)
+        .contains(&stars)
+        .then(|| (stars * 2.0).round() as u8)
+}
+
+fn parse_year(value: &str) -> Option<i32> {
+    value.trim().get(..4)?.parse().ok()
+}
+
+/// Parses the rated rows of an export. Unrated rows, which RYM exports with a
+/// rating of 0, are left out.
+pub fn read_rows(reader: impl Read) -> Result<Vec<RymRow>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let columns = Columns::from_headers(reader.headers()?)?;
+    let mut rows = Vec::new();
+
+    for record in reader.records() {
+        let record = record.context("Couldn't parse the file")?;
+        let field = |idx: usize| record.get(idx).unwrap_or_default().trim();
+
+        let Some(rating) = convert_rating(field(columns.rating)) else {
+            continue;
+        };
+
+        let artist = columns
+            .artist
+            .iter()
+            .map(|idx| field(*idx))
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        rows.push(RymRow {
+            artist,
+            title: field(columns.title).to_string(),
+            year: columns.date.and_then(|idx| parse_year(field(idx))),
+            rating,
+        });
+    }
+
+    Ok(rows)
+}
+
+/// A row that couldn't be matched to a single release group, along with the
+/// release groups it might be.
+pub struct ReviewItem {
+    pub row: RymRow,
+    pub artist: Option<ArtistSearchResult>,
+    pub candidates: StatefulList<Release>,
+    /// Why the row couldn't be looked up, if the lookup failed.
+    pub error: Option<String>,
+}
+
+pub enum Resolution {
+    Matched(ArtistSearchResult, Release),
+    Review(ReviewItem),
+}
+
+/// Looks the row up on MusicBrainz. Rows are matched automatically only if
+/// exactly one release group of the artist has the same title, and the same
+/// year if the row has one. Rows that couldn't be looked up are left for
+/// review along with the error, so that the lookup can be retried.
+pub async fn resolve(row: RymRow) -> Resolution {
+    let (artist, mut candidates) = match find_candidates(&row).await {
+        Ok((Some(artist), candidates)) => (artist, candidates),
+        result => {
+            return Resolution::Review(ReviewItem {
+                row,
+                artist: None,
+                candidates: StatefulList::with_items(Vec::new()),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+    };
+
+    let matches = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, release)| {
+            normalize_title(&release.title) == normalize_title(&row.title)
+                && row
+                    .year
+                    .is_none_or(|year| release.known_year() == Some(year))
+        })
+        .map(|(idx, _)| idx)
+        .collect::<Vec<usize>>();
+
+    if let [idx] = matches[..] {
+        let mut release = candidates.swap_remove(idx);
+        release.rating = Some(row.rating);
+
+        return Resolution::Matched(artist, release);
+    }
+
+    Resolution::Review(ReviewItem {
+        row,
+        artist: Some(artist),
+        candidates: StatefulList::with_items(candidates),
+        error: None,
+    })
+}
+
+/// The artist that best matches the row, along with its release groups that
+/// match the title of the row.
+async fn find_candidates(
+    row: &RymRow,
+) -> Result<(Option<ArtistSearchResult>, Vec<Release>), Error> {
+    let artists = musicbrainz::search_artist(&row.artist).await?;
+
+    let artist = artists
+        .iter()
+        .position(|artist| artist.name.eq_ignore_ascii_case(&row.artist))
+        .or((!artists.is_empty()).then_some(0))
+        .map(|idx| artists.into_iter().nth(idx).unwrap());
+
+    let Some(artist) = artist else {
+        return Ok((None, Vec::new()));
+    };
+
+    let candidates = musicbrainz::search_release_groups(&artist.id, &row.title).await?;
+
+    Ok((Some(artist), candidates))
+}
+
+fn normalize_title(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+/// Stores a matched rating unless the release group is rated already, in
+/// which case the existing rating wins. Returns whether it was stored.
+pub fn save_match(
+    conn: &Connection,
+    artist: &ArtistSearchResult,
+    release: &Release,
+) -> Result<bool> {
+    if database::get_rating(conn, &release.id)?.is_some() {
+        return Ok(false);
+    }
+
+    database::add_artist(conn, &artist.id, &artist.name)?;
+    database::add_release(conn, &artist.id, release)?;
+
+    Ok(true)
+}
+
+/// The rows that are waiting to be matched by hand.
+pub struct Review {
+    pub queue: Vec<ReviewItem>,
+    pub current: usize,
+    pub matched: usize,
+    pub skipped: usize,
+}
+
+impl Review {
+    pub fn new(queue: Vec<ReviewItem>) -> Self {
+        Self {
+            queue,
+            current: 0,
+            matched: 0,
+            skipped: 0,
+        }
+    }
+
+    pub fn current_item(&mut self) -> Option<&mut ReviewItem> {
+        self.queue.get_mut(self.current)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.current >= self.queue.len()
+    }
+
+    /// Rates the selected candidate of the current row and moves on to the
+    /// next one.
+    pub fn pick(&mut self, conn: &Connection) -> Result<()> {
+        let Some(item) = self.queue.get(self.current) else {
+            return Ok(());
+        };
+
+        let (Some(artist), Some(release)) = (&item.artist, item.candidates.get_selected()) else {
+            return Ok(());
+        };
+
+        let mut release = release.clone();
+        release.rating = Some(item.row.rating);
+
+        if save_match(conn, artist, &release)? {
+            self.matched += 1;
+        } else {
+            self.skipped += 1;
+        }
+
+        self.current += 1;
+
+        Ok(())
+    }
+
+    /// Looks the current row up again, rating it right away if it can be
+    /// matched now.
+    pub async fn retry(&mut self, conn: &Connection) -> Result<()> {
+        let Some(item) = self.queue.get(self.current) else {
+            return Ok(());
+        };
+
+        match resolve(item.row.clone()).await {
+            Resolution::Matched(artist, release) => {
+                if save_match(conn, &artist, &release)? {
+                    self.matched += 1;
+                } else {
+                    self.skipped += 1;
+                }
+
+                self.current += 1;
+            }
+            Resolution::Review(item) => self.queue[self.current] = item,
+        }
+
+        Ok(())
+    }
+
+    pub fn skip(&mut self) {
+        if !self.is_done() {
+            self.skipped += 1;
+            self.current += 1;
+        }
+    }
+}
+
+pub async fn run_review<B: Backend>(
+    terminal: &mut Terminal<B>,
+    conn: &Connection,
+    review: &mut Review,
+) -> Result<()> {
+    let mut events = EventStream::new();
+
+    while !review.is_done() {
+        terminal.draw(|f| ui::render_review(f, review))?;
+
+        let Some(event) = events.next().await else {
+            break;
+        };
+
+        let Event::Key(key) = event? else {
+            continue;
+        };
+
+        let Some(item) = review.current_item() else {
+            break;
+        };
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('j') | KeyCode::Down => item.candidates.next(),
+            KeyCode::Char('k') | KeyCode::Up => item.candidates.previous(),
+            KeyCode::Char('s') => review.skip(),
+            KeyCode::Char('r') => review.retry(conn).await?,
+            KeyCode::Enter => review.pick(conn)?,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_stars_to_ratings() {
+        assert_eq!(convert_rating("0.5"), Some(1));
+        assert_eq!(convert_rating("3.5"), Some(7));
+        assert_eq!(convert_rating(" 5 "), Some(10));
+        assert_eq!(convert_rating("0"), None);
+        assert_eq!(convert_rating("5.5"), None);
+        assert_eq!(convert_rating("great"), None);
+    }
+
+    #[test]
+    fn reads_exports_with_a_single_artist_column() {
+        let csv = "\
+RYM Album,Artist,Title,Release_Date,Rating
+1,Radiohead,OK Computer,1997-05-21,5
+2,Radiohead,Kid A,2000,0
+3,Boards of Canada,Geogaddi,,3.5
+";
+        let rows = read_rows(csv.as_bytes()).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].to_string(), "Radiohead - (1997) OK Computer");
+        assert_eq!(rows[0].rating, 10);
+        assert_eq!(rows[1].year, None);
+        assert_eq!(rows[1].rating, 7);
+    }
+
+    #[test]
+    fn joins_split_artist_names() {
+        let csv = "\
+RYM Album, First Name,Last Name,First Name localized, Last Name localized,Title,Release_Date,Rating,Ownership,Purchase Date,Media Type,Review
+1,,Radiohead,,,OK Computer,1997,4.5,,,,
+2,Aphex,Twin,,,Drukqs,2001,3,,,,
+";
+        let rows = read_rows(csv.as_bytes()).unwrap();
+
+        assert_eq!(rows[0].artist, "Radiohead");
+        assert_eq!(rows[0].rating, 9);
+        assert_eq!(rows[1].artist, "Aphex Twin");
+    }
+
+    #[test]
+    fn rejects_files_without_ratings() {
+        assert!(read_rows("Artist,Title\nRadiohead,Kid A\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{app::ReleaseType, musicbrainz::Release, rating::Rated};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// How many artists are ranked by their mean rating.
+const TOP_ARTISTS: usize = 10;
+
+/// How many of the most rated genres are listed.
+const TOP_GENRES: usize = 10;
+
+/// How many of the highest rated releases are listed.
+const TOP_RELEASES: usize = 10;
+
+/// Artists need this many ratings to be ranked by default, so that a single
+/// rating doesn't put an artist on top.
+pub const DEFAULT_MIN_RATINGS: usize = 3;
+
+#[derive(Serialize)]
+pub struct DecadeStats {
+    /// First year of the decade, such as 1990.
+    pub decade: i32,
+    pub count: usize,
+    pub average: f64,
+}
+
+#[derive(Serialize)]
+pub struct ArtistStats {
+    pub artist_id: String,
+    pub artist_name: String,
+    pub count: usize,
+    pub average: f64,
+}
+
+#[derive(Serialize)]
+pub struct GenreStats {
+    pub genre: String,
+    pub count: usize,
+    pub average: f64,
+}
+
+#[derive(Serialize)]
+pub struct ReleaseStats {
+    pub release_id: String,
+    pub artist_name: String,
+    pub title: String,
+    pub rating: u8,
+}
+
+#[derive(Serialize)]
+pub struct TypeStats {
+    #[serde(rename = "type")]
+    pub release_type: String,
+    pub count: usize,
+}
+
+/// An overview of the ratings.
+#[derive(Serialize)]
+pub struct Stats {
+    pub total: usize,
+    pub average: Option<f64>,
+    /// The number of releases rated 1 to 10.
+    pub distribution: [usize; 10],
+    pub decades: Vec<DecadeStats>,
+    pub top_artists: Vec<ArtistStats>,
+    pub release_types: Vec<TypeStats>,
+    /// The most rated genres.
+    pub genres: Vec<GenreStats>,
+    pub top_releases: Vec<ReleaseStats>,
+}
+
+impl Stats {
+    /// Computes the stats of the rated releases. Only artists with at least
+    /// `min_ratings` ratings are ranked. Ratings outside of 1 to 10, which the
+    /// database doesn't rule out, are left out.
+    pub fn compute(ratings: &[Rated], min_ratings: usize) -> Self {
+        let rated = ratings
+            .iter()
+            .filter_map(|item| item.rating.map(|rating| (item, rating)))
+            .filter(|(_, rating)| (1..=10).contains(rating))
+            .collect::<Vec<(&Rated, u8)>>();
+
+        let mut distribution = [0; 10];
+        let mut decades = HashMap::<i32, Vec<u8>>::new();
+        let mut artists = HashMap::<&str, (&str, Vec<u8>)>::new();
+        let mut release_types = HashMap::<String, usize>::new();
+        let mut genres = HashMap::<&str, Vec<u8>>::new();
+
+        for (item, rating) in &rated {
+            distribution[*rating as usize - 1] += 1;
+
+            if let Some(year) = item.year {
+                decades
+                    .entry(year - year.rem_euclid(10))
+                    .or_default()
+                    .push(*rating);
+            }
+
+            artists
+                .entry(&item.artist_id)
+                .or_insert_with(|| (&item.artist_name, Vec::new()))
+                .1
+                .push(*rating);
+
+            let release_type = match item.group_type.to_string() {
+                release_type if release_type.is_empty() => "Other".to_string(),
+                release_type => release_type,
+            };
+            *release_types.entry(release_type).or_default() += 1;
+
+            for genre in item.effective_genres() {
+                genres.entry(genre).or_default().push(*rating);
+            }
+        }
+
+        let mut decades = decades
+            .into_iter()
+            .map(|(decade, ratings)| DecadeStats {
+                decade,
+                count: ratings.len(),
+                average: mean(&ratings),
+            })
+            .collect::<Vec<DecadeStats>>();
+        decades.sort_by_key(|stats| stats.decade);
+
+        let mut top_artists = artists
+            .into_iter()
+            .filter(|(_, (_, ratings))| ratings.len() >= min_ratings)
+            .map(|(artist_id, (artist_name, ratings))| ArtistStats {
+                artist_id: artist_id.to_string(),
+                artist_name: artist_name.to_string(),
+                count: ratings.len(),
+                average: mean(&ratings),
+            })
+            .collect::<Vec<ArtistStats>>();
+        // Ties go to the artist with more ratings, then alphabetically.
+        top_artists.sort_by(|a, b| {
+            b.average
+                .total_cmp(&a.average)
+                .then(b.count.cmp(&a.count))
+                .then_with(|| a.artist_name.cmp(&b.artist_name))
+        });
+        top_artists.truncate(TOP_ARTISTS);
+
+        let mut release_types = release_types
+            .into_iter()
+            .map(|(release_type, count)| TypeStats {
+                release_type,
+                count,
+            })
+            .collect::<Vec<TypeStats>>();
+        release_types.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.release_type.cmp(&b.release_type))
+        });
+
+        let mut genres = genres
+            .into_iter()
+            .map(|(genre, ratings)| GenreStats {
+                genre: genre.to_string(),
+                count: ratings.len(),
+                average: mean(&ratings),
+            })
+            .collect::<Vec<GenreStats>>();
+        genres.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(b.average.total_cmp(&a.average))
+                .then_with(|| a.genre.cmp(&b.genre))
+        });
+        genres.truncate(TOP_GENRES);
+
+        let mut top_releases = rated
+            .iter()
+            .map(|(item, rating)| ReleaseStats {
+                release_id: item.release_id.clone(),
+                artist_name: item.artist_name.clone(),
+                title: item.title.clone(),
+                rating: *rating,
+            })
+            .collect::<Vec<ReleaseStats>>();
+        top_releases.sort_by(|a, b| {
+            b.rating
+                .cmp(&a.rating)
+                .then_with(|| a.artist_name.cmp(&b.artist_name))
+                .then_with(|| a.title.cmp(&b.title))
+        });
+        top_releases.truncate(TOP_RELEASES);
+
+        let ratings = rated.iter().map(|(_, rating)| *rating).collect::<Vec<u8>>();
+
+        Stats {
+            total: ratings.len(),
+            average: (!ratings.is_empty()).then(|| mean(&ratings)),
+            distribution,
+            decades,
+            top_artists,
+            release_types,
+            genres,
+            top_releases,
+        }
+    }
+}
+
+fn mean(ratings: &[u8]) -> f64 {
+    ratings.iter().map(|rating| *rating as f64).sum::<f64>() / ratings.len() as f64
+}
+
+/// How much of a type of release groups of an artist is rated.
+pub struct TypeCompletion {
+    pub release_type: ReleaseType,
+    pub rated: usize,
+    pub total: usize,
+}
+
+/// An overview of the ratings given to the release groups of an artist.
+pub struct ArtistSummary {
+    /// Completion of each type, in the order the types are listed in.
+    pub types: Vec<TypeCompletion>,
+    pub total: usize,
+    /// Ratings from the highest.
+    ratings: Vec<u8>,
+}
+
+impl ArtistSummary {
+    pub fn compute<'a>(releases: impl Iterator<Item = &'a Release>) -> Self {
+        let mut types = Vec::<TypeCompletion>::new();
+        let mut ratings = Vec::new();
+        let mut total = 0;
+
+        for release in releases {
+            let idx = match types
+                .iter()
+                .position(|completion| completion.release_type == release.group_type)
+            {
+                Some(idx) => idx,
+                None => {
+                    types.push(TypeCompletion {
+                        release_type: release.group_type.clone(),
+                        rated: 0,
+                        total: 0,
+                    });
+                    types.len() - 1
+                }
+            };
+            let completion = &mut types[idx];
+
+            completion.total += 1;
+            total += 1;
+
+            if let Some(rating) = release.rating {
+                completion.rated += 1;
+                ratings.push(rating);
+            }
+        }
+
+        types.sort_by_key(|completion| completion.release_type.sort_key());
+        ratings.sort_unstable_by(|a, b| b.cmp(a));
+
+        Self {
+            types,
+            total,
+            ratings,
+        }
+    }
+
+    pub fn rated(&self) -> usize {
+        self.ratings.len()
+    }
+
+    pub fn average(&self) -> Option<f64> {
+        (!self.ratings.is_empty()).then(|| mean(&self.ratings))
+    }
+
+    pub fn median(&self) -> Option<f64> {
+        let len = self.ratings.len();
+
+        match len {
+            0 => None,
+            _ if len % 2 == 1 => Some(self.ratings[len / 2] as f64),
+            _ => Some(mean(&self.ratings[len / 2 - 1..=len / 2])),
+        }
+    }
+
+    /// The place of a rating among the ratings of the artist. Releases with
+    /// the same rating share their place.
+    pub fn rank(&self, rating: u8) -> usize {
+        self.ratings.partition_point(|other| *other > rating) + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rating::tests::rated;
+
+    fn ratings() -> Vec<Rated> {
+        let mut ratings = vec![
+            rated("Alpha", "One", 8, Some(1991)),
+            rated("Alpha", "Two", 6, Some(1999)),
+            rated("Alpha", "Three", 10, Some(2004)),
+            rated("Beta", "Four", 9, None),
+            rated("Beta", "Five", 3, Some(2001)),
+        ];
+        ratings[0].genres = vec!["rock".to_string(), "pop".to_string()];
+        ratings[1].genres = vec!["rock".to_string()];
+        ratings[3].group_type = ReleaseType::parse("Album + Live");
+        ratings
+    }
+
+    #[test]
+    fn computes_the_overview() {
+        let stats = Stats::compute(&ratings(), 1);
+
+        assert_eq!(stats.total, 5);
+        assert_eq!(stats.average, Some(7.2));
+        assert_eq!(stats.distribution, [0, 0, 1, 0, 0, 1, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn groups_ratings_by_decade_and_type() {
+        let stats = Stats::compute(&ratings(), 1);
+
+        let decades = stats
+            .decades
+            .iter()
+            .map(|decade| (decade.decade, decade.count, decade.average))
+            .collect::<Vec<(i32, usize, f64)>>();
+        assert_eq!(decades, [(1990, 2, 7.0), (2000, 2, 6.5)]);
+
+        assert_eq!(stats.release_types[0].release_type, "Album");
+        assert_eq!(stats.release_types[0].count, 4);
+        assert_eq!(stats.release_types[1].count, 1);
+    }
+
+    #[test]
+    fn ranks_artists_with_enough_ratings() {
+        let stats = Stats::compute(&ratings(), 3);
+
+        assert_eq!(stats.top_artists.len(), 1);
+        assert_eq!(stats.top_artists[0].artist_name, "Alpha");
+        assert_eq!(stats.top_artists[0].average, 8.0);
+
+        let stats = Stats::compute(&ratings(), 2);
+        assert_eq!(stats.top_artists[0].artist_name, "Alpha");
+        assert_eq!(stats.top_artists[1].artist_name, "Beta");
+    }
+
+    #[test]
+    fn lists_genres_and_top_releases() {
+        let stats = Stats::compute(&ratings(), 1);
+
+        assert_eq!(stats.genres[0].genre, "rock");
+        assert_eq!(stats.genres[0].count, 2);
+        assert_eq!(stats.genres[0].average, 7.0);
+        assert_eq!(stats.genres[1].genre, "pop");
+
+        let titles = stats
+            .top_releases
+            .iter()
+            .map(|release| release.title.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(titles, ["Three", "Four", "One", "Two", "Five"]);
+    }
+
+    #[test]
+    fn skips_ratings_out_of_range() {
+        let mut ratings = ratings();
+        ratings[0].rating = Some(0);
+        ratings[1].rating = Some(11);
+        ratings[2].rating = None;
+
+        let stats = Stats::compute(&ratings, 1);
+
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.distribution.iter().sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn computes_nothing_without_ratings() {
+        let stats = Stats::compute(&[], 1);
+
+        assert_eq!(stats.total, 0);
+        assert_eq!(stats.average, None);
+        assert!(stats.decades.is_empty());
+    }
+}
@@ -1,17 +1,68 @@
 use crate::{
-    app::{App, ListItemType},
-    musicbrainz::ArtistSearchResult,
+    app::{App, Details, ListItemType, Tab, TagEditor},
+    coverart::{Cover, CoverPlacement, Graphics, half_blocks},
+    musicbrainz::CommunityRating,
+    rym::Review,
+    stats::{self, ArtistSummary},
+    utils::join_tags,
 };
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use ratatui::{
     Frame,
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, List, ListItem, ListState},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, BorderType, Borders, Clear, List, ListItem, ListState,
+        Paragraph, Tabs, Wrap,
+    },
 };
 
+/// Width of the cover panel next to discographies, which are only given one
+/// on terminals of at least `MIN_WIDTH_FOR_COVER` columns.
+const COVER_WIDTH: u16 = 32;
+const MIN_WIDTH_FOR_COVER: u16 = 90;
+
+/// Width of the detail panel, which also holds the review and history.
+const DETAILS_WIDTH: u16 = 42;
+
+/// Items that can be narrowed down with the fuzzy filter of a list.
+pub trait Searchable {
+    /// The text the filter matches against. The matched characters are
+    /// highlighted in it, so it should be the text the item is shown with.
+    fn search_text(&self) -> String;
+
+    /// Headers are kept as long as any of the items under them match.
+    fn is_header(&self) -> bool {
+        false
+    }
+}
+
+/// A list whose items can be narrowed down as a query is typed.
+pub trait FuzzyFilter {
+    fn query(&self) -> &str;
+
+    /// Narrows the list down to the items matching the query, or shows every
+    /// item again if it's empty.
+    fn set_query(&mut self, query: &str);
+}
+
+/// The state of a filtered list. Every item remembers its position in the
+/// full list, so that the filtered out items can be put back in place.
+struct ListFilter<T> {
+    query: String,
+    /// Positions of the shown items.
+    positions: Vec<usize>,
+    /// Indices of the matched characters of the shown items.
+    matches: Vec<Vec<usize>>,
+    hidden: Vec<(usize, T)>,
+}
+
 pub struct StatefulList<T> {
     state: ListState,
+    /// The shown items, which are only the matching ones while filtered.
     pub items: Vec<T>,
+    filter: Option<ListFilter<T>>,
 }
 
 impl<T> StatefulList<T> {
@@ -19,6 +70,7 @@ impl<T> StatefulList<T> {
         let mut list = StatefulList {
             state: ListState::default(),
             items,
+            filter: None,
         };
 
         if !list.items.is_empty() {
@@ -29,6 +81,10 @@ impl<T> StatefulList<T> {
     }
 
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -43,6 +99,10 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -56,6 +116,23 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 
+    pub fn select(&mut self, index: Option<usize>) {
+        self.state.select(index);
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    /// The position of a shown item in the full list, which only differs from
+    /// its index while the list is filtered.
+    pub fn position(&self, index: usize) -> usize {
+        match &self.filter {
+            Some(filter) => filter.positions[index],
+            None => index,
+        }
+    }
+
     pub fn get_selected(&self) -> Option<&T> {
         match self.state.selected() {
             Some(i) => Some(&self.items[i]),
@@ -69,24 +146,317 @@ impl<T> StatefulList<T> {
             None => None,
         }
     }
+
+    /// Every item, including the ones that are filtered out.
+    pub fn iter_all(&self) -> impl Iterator<Item = &T> {
+        let hidden = self.filter.iter().flat_map(|filter| &filter.hidden);
+        self.items.iter().chain(hidden.map(|(_, item)| item))
+    }
+
+    pub fn iter_all_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let hidden = self.filter.iter_mut().flat_map(|filter| &mut filter.hidden);
+        self.items.iter_mut().chain(hidden.map(|(_, item)| item))
+    }
+
+    /// Takes every item out of the list in their original order. The query is
+    /// kept, to be applied to the items given to `set_items`.
+    pub fn take_items(&mut self) -> Vec<T> {
+        self.restore();
+        std::mem::take(&mut self.items)
+    }
+
+    /// Indices of the characters of the shown item that the query matched.
+    pub fn matched_indices(&self, index: usize) -> &[usize] {
+        self.filter
+            .as_ref()
+            .and_then(|filter| filter.matches.get(index))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Puts the filtered out items back in place, leaving the query as is.
+    fn restore(&mut self) {
+        let Some(filter) = &mut self.filter else {
+            return;
+        };
+
+        let mut items = std::mem::take(&mut self.items)
+            .into_iter()
+            .zip(filter.positions.drain(..))
+            .map(|(item, position)| (position, item))
+            .chain(filter.hidden.drain(..))
+            .collect::<Vec<(usize, T)>>();
+        items.sort_by_key(|(position, _)| *position);
+
+        self.items = items.into_iter().map(|(_, item)| item).collect();
+        filter.matches.clear();
+    }
+}
+
+impl<T: Searchable> StatefulList<T> {
+    /// Replaces the items, narrowing them down with the current query.
+    pub fn set_items(&mut self, items: Vec<T>) {
+        self.items = items;
+
+        if let Some(filter) = self.filter.take() {
+            self.apply_query(filter.query);
+        }
+    }
+
+    /// Filters the items, which must all be shown. The selection moves to the
+    /// closest shown item to the one that was selected.
+    fn apply_query(&mut self, query: String) {
+        let selected = self.state.selected().unwrap_or_default();
+
+        if query.is_empty() {
+            self.filter = None;
+            self.state.select(self.closest_selectable(selected));
+            return;
+        }
+
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let mut matches = self
+            .items
+            .iter()
+            .map(|item| match item.is_header() {
+                true => None,
+                false => matcher
+                    .fuzzy_indices(&item.search_text(), &query)
+                    .map(|(_, indices)| indices),
+            })
+            .collect::<Vec<Option<Vec<usize>>>>();
+
+        let mut has_match = false;
+
+        for (item, matched) in self.items.iter().zip(&mut matches).rev() {
+            if item.is_header() {
+                if has_match {
+                    *matched = Some(Vec::new());
+                }
+                has_match = false;
+            } else {
+                has_match |= matched.is_some();
+            }
+        }
+
+        let mut filter = ListFilter {
+            query,
+            positions: Vec::new(),
+            matches: Vec::new(),
+            hidden: Vec::new(),
+        };
+        let mut shown = Vec::new();
+
+        for (position, (item, matched)) in std::mem::take(&mut self.items)
+            .into_iter()
+            .zip(matches)
+            .enumerate()
+        {
+            match matched {
+                Some(matched) => {
+                    shown.push(item);
+                    filter.positions.push(position);
+                    filter.matches.push(matched);
+                }
+                None => filter.hidden.push((position, item)),
+            }
+        }
+
+        self.items = shown;
+        self.filter = Some(filter);
+        self.state.select(self.closest_selectable(selected));
+    }
+
+    /// The first shown item at or after the position in the full list, or the
+    /// last one if there are none after it. Headers are never selected.
+    fn closest_selectable(&self, position: usize) -> Option<usize> {
+        let selectable = |idx: &usize| !self.items[*idx].is_header();
+
+        (0..self.items.len())
+            .filter(selectable)
+            .find(|idx| self.position(*idx) >= position)
+            .or_else(|| (0..self.items.len()).rev().find(selectable))
+    }
+}
+
+impl<T: Searchable> FuzzyFilter for StatefulList<T> {
+    fn query(&self) -> &str {
+        self.filter
+            .as_ref()
+            .map_or("", |filter| filter.query.as_str())
+    }
+
+    fn set_query(&mut self, query: &str) {
+        // The selection is kept by its position in the full list.
+        let selected = self.state.selected().map(|idx| self.position(idx));
+
+        self.restore();
+        self.state.select(selected);
+        self.apply_query(query.to_string());
+    }
 }
 
 pub fn render(f: &mut Frame, app: &mut App) {
-    if app.releases.is_some() {
-        render_releases(f, app);
-    } else if let Some(results) = &mut app.search_results {
-        render_search_results(f, results);
+    app.cover_placement = None;
+
+    let bottom_height = if app.search_prompt.active
+        || app.filter_prompt.is_some()
+        || app.find_prompt.is_some()
+        || app.tag_editor.is_some()
+    {
+        3
+    } else if app.status.is_some() || app.pending_search.is_some() || app.pending_unrate.is_some() {
+        1
     } else {
-        render_ratings(f, app);
+        0
+    };
+
+    let [tabs_area, main_area, bottom_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(bottom_height),
+    ])
+    .areas(f.area());
+
+    render_tabs(f, app, tabs_area);
+
+    let main_area = if app.show_details {
+        let [main_area, details_area] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(DETAILS_WIDTH)])
+                .areas(main_area);
+        render_details(f, app, details_area);
+        main_area
+    } else if app.show_history {
+        let [main_area, history_area] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(32)]).areas(main_area);
+        render_history(f, app, history_area);
+        main_area
+    } else {
+        main_area
+    };
+
+    if app
+        .discography()
+        .is_some_and(|discography| discography.tracklist.is_some())
+    {
+        render_tracklist(f, app, main_area);
+    } else if app.discography().is_some() {
+        render_releases(f, app, main_area);
+    } else {
+        match app.tab {
+            Tab::Search => render_search_results(f, app, main_area),
+            Tab::Ratings => render_ratings(f, app, main_area),
+            Tab::Backlog => render_backlog(f, app, main_area),
+            Tab::Stats => render_stats(f, app, main_area),
+        }
+    }
+
+    if app.review_editor.is_some() {
+        render_review_editor(f, app, main_area);
     }
+
+    if app.search_prompt.active {
+        render_search_prompt(f, app, bottom_area);
+    } else if let Some(editor) = &app.tag_editor {
+        render_tag_editor(f, editor, bottom_area);
+    } else if let Some(query) = &app.filter_prompt {
+        render_prompt(f, "Filter Ratings", query, bottom_area);
+    } else if let Some(query) = &app.find_prompt {
+        render_prompt(f, "Find", query, bottom_area);
+    } else if let Some(pending) = &app.pending_unrate {
+        f.render_widget(
+            Line::from(format!(
+                " Remove the rating of {}? (y/n)",
+                pending.description
+            ))
+            .yellow(),
+            bottom_area,
+        );
+    } else if let Some(query) = &app.pending_search {
+        f.render_widget(
+            Line::from(format!(
+                " {} Searching for \"{query}\"",
+                spinner(app.spinner_frame)
+            ))
+            .yellow(),
+            bottom_area,
+        );
+    } else if let Some(status) = &app.status {
+        f.render_widget(Line::from(format!(" {status}")).red(), bottom_area);
+    }
+}
+
+fn render_tabs(f: &mut Frame, app: &App, area: Rect) {
+    let tabs = Tabs::new(Tab::ALL.map(Tab::title))
+        .select(Tab::ALL.iter().position(|tab| *tab == app.tab))
+        .highlight_style(Style::default().cyan().bold());
+
+    f.render_widget(tabs, area);
+}
+
+fn render_search_prompt(f: &mut Frame, app: &App, area: Rect) {
+    render_prompt(f, "Search Artist", &app.search_prompt.query, area);
+}
+
+fn render_prompt(f: &mut Frame, title: &str, query: &str, area: Rect) {
+    let prompt = Paragraph::new(query).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(title)
+            .title_style(Style::default().cyan().bold()),
+    );
+
+    f.render_widget(prompt, area);
+    f.set_cursor_position((area.x + 1 + query.chars().count() as u16, area.y + 1));
 }
 
-pub fn render_search_results(f: &mut Frame, results: &mut StatefulList<ArtistSearchResult>) {
+fn render_tag_editor(f: &mut Frame, editor: &TagEditor, area: Rect) {
+    let mut text = vec![Span::raw(editor.text.as_str())];
+
+    if let Some(completion) = editor.completion() {
+        text.push(Span::styled(completion, Style::default().dim()));
+    }
+
+    let prompt = Paragraph::new(Line::from(text)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!("Tags of {}", editor.description))
+            .title_style(Style::default().cyan().bold())
+            .title_bottom(Line::from(" Tab: complete  Enter: save  Esc: cancel ").dim()),
+    );
+
+    f.render_widget(prompt, area);
+    f.set_cursor_position((area.x + 1 + editor.text.chars().count() as u16, area.y + 1));
+}
+
+pub fn render_search_results(f: &mut Frame, app: &mut App, area: Rect) {
+    let title = cache_status_title("Search Results".to_string(), app.search_stale, app);
+
+    let Some(results) = &mut app.search_results else {
+        let hint = Paragraph::new("Press s to search for an artist").block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(title)
+                .title_style(Style::default().cyan().bold()),
+        );
+        f.render_widget(hint, area);
+        return;
+    };
+
     let artists = results
         .items
         .iter()
-        .map(|ar| ar.to_string())
-        .map(Span::raw)
+        .enumerate()
+        .map(|(idx, ar)| {
+            highlight_matches(
+                &ar.to_string(),
+                results.matched_indices(idx),
+                Style::default(),
+            )
+        })
+        .map(Line::from)
         .map(ListItem::new)
         .collect::<Vec<ListItem>>();
 
@@ -101,21 +471,62 @@ pub fn render_search_results(f: &mut Frame, results: &mut StatefulList<ArtistSea
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .title("Search Results")
+                .title(title)
                 .title_style(Style::default().cyan().bold()),
         );
 
-    f.render_stateful_widget(artists, f.area(), &mut results.state);
+    f.render_stateful_widget(artists, area, &mut results.state);
 }
 
-pub fn render_releases(f: &mut Frame, app: &mut App) {
-    let Some(artist_name) = app.get_selected_artist().map(|artist| artist.name.clone()) else {
+pub fn render_releases(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(discography) = app.discography() else {
         return;
     };
 
-    let Some(releases) = &mut app.releases else {
+    let title = match &discography.pending {
+        Some(pending) if pending.count == 0 => {
+            format!("{} {}", discography.artist_name, spinner(app.spinner_frame))
+        }
+        Some(pending) => format!(
+            "{} {} {}/{}",
+            discography.artist_name,
+            spinner(app.spinner_frame),
+            pending.fetched,
+            pending.count
+        ),
+        None => discography.artist_name.clone(),
+    };
+    let title = cache_status_title(title, discography.stale, app);
+    let summary = discography.summary();
+
+    let area = if area.width >= MIN_WIDTH_FOR_COVER {
+        let [area, cover_area] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(COVER_WIDTH)]).areas(area);
+        render_cover(f, app, cover_area);
+        area
+    } else {
+        area
+    };
+
+    let Some(discography) = &mut app.releases else {
         return;
     };
+    let ranked = discography.ranked;
+    let releases = &mut discography.list;
+
+    let [summary_area, area] =
+        Layout::vertical([Constraint::Length(4), Constraint::Fill(1)]).areas(area);
+    render_artist_summary(f, &summary, ranked, summary_area);
+
+    if releases.items.is_empty() {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(title)
+            .title_style(Style::default().cyan().bold());
+        f.render_widget(block, area);
+        return;
+    }
 
     let selected_index = releases.state.selected().unwrap();
     let mut list_items = Vec::new();
@@ -126,12 +537,33 @@ pub fn render_releases(f: &mut Frame, app: &mut App) {
                 r#type.to_string(),
                 Style::default().fg(Color::Green),
             )],
-            ListItemType::Release(release) => rating_line(
-                &release.to_string(),
-                release.rating,
-                idx == selected_index,
-                app.currently_rating,
-            ),
+            ListItemType::Release(release) => {
+                let mut line = rating_line(
+                    &release.to_string(),
+                    releases.matched_indices(idx),
+                    release.rating,
+                    release.community,
+                    idx == selected_index,
+                    app.currently_rating,
+                );
+                line.extend(track_average(release.track_average));
+
+                if ranked && let Some(rating) = release.rating {
+                    line.push(Span::styled(
+                        format!(" #{}", summary.rank(rating)),
+                        Style::default().fg(Color::Magenta).dim(),
+                    ));
+                }
+
+                if release.in_backlog {
+                    line.push(Span::styled(
+                        " [backlog]",
+                        Style::default().fg(Color::Blue).dim(),
+                    ));
+                }
+
+                line
+            }
         };
 
         list_items.push(ListItem::new(Line::from(item)));
@@ -141,42 +573,774 @@ pub fn render_releases(f: &mut Frame, app: &mut App) {
         Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .title(artist_name)
+            .title(title)
             .title_style(Style::default().cyan().bold()),
     );
-    f.render_stateful_widget(list, f.area(), &mut releases.state);
+    f.render_stateful_widget(list, area, &mut releases.state);
+}
+
+/// Draws the cover of the selected release, or leaves the panel empty for it
+/// to be drawn with terminal graphics after the frame.
+fn render_cover(f: &mut Frame, app: &mut App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title("Cover")
+        .title_style(Style::default().cyan().bold());
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some(ListItemType::Release(release)) = app
+        .discography()
+        .and_then(|discography| discography.list.get_selected())
+    else {
+        return;
+    };
+    let placement = CoverPlacement {
+        release_id: release.id.clone(),
+        area: inner,
+    };
+
+    match app.covers.get(&placement.release_id) {
+        Some(Cover::Image(image)) if app.graphics == Graphics::HalfBlocks => {
+            let lines = match &app.half_block_cover {
+                Some((drawn, lines)) if *drawn == placement => lines.clone(),
+                _ => {
+                    let lines = half_blocks(image, inner);
+                    app.half_block_cover = Some((placement, lines.clone()));
+                    lines
+                }
+            };
+
+            f.render_widget(Paragraph::new(lines), inner);
+        }
+        Some(Cover::Image(_)) => app.cover_placement = Some(placement),
+        Some(Cover::Loading) | None => f.render_widget(
+            Line::from(spinner(app.spinner_frame).to_string()).yellow(),
+            inner,
+        ),
+        Some(Cover::Missing) => f.render_widget(Line::from("No cover").dim(), inner),
+    }
+}
+
+fn render_artist_summary(f: &mut Frame, summary: &ArtistSummary, ranked: bool, area: Rect) {
+    let mut overview = vec![Span::raw(format!(
+        "Rated {}/{}",
+        summary.rated(),
+        summary.total
+    ))];
+
+    if let (Some(average), Some(median)) = (summary.average(), summary.median()) {
+        overview.push(Span::raw(format!("  ⌀ {average:.2}  median {median:.1}")));
+    }
+
+    let types = summary
+        .types
+        .iter()
+        .map(|completion| {
+            let release_type = match completion.release_type.to_string() {
+                release_type if release_type.is_empty() => "Other".to_string(),
+                release_type => release_type,
+            };
+
+            Span::styled(
+                format!("{release_type} {}/{}  ", completion.rated, completion.total),
+                Style::default().fg(Color::Green),
+            )
+        })
+        .collect::<Vec<Span>>();
+
+    let summary = Paragraph::new(vec![Line::from(overview), Line::from(types)])
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(if ranked {
+                    "Summary (ranked)"
+                } else {
+                    "Summary"
+                })
+                .title_style(Style::default().cyan().bold()),
+        );
+
+    f.render_widget(summary, area);
 }
 
-pub fn render_ratings(f: &mut Frame, app: &mut App) {
-    let Some(rated) = &mut app.rated_list else {
+pub fn render_tracklist(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(tracklist) = app
+        .discography()
+        .and_then(|discography| discography.tracklist.as_ref())
+    else {
+        return;
+    };
+
+    let title = if tracklist.loading {
+        format!("{} {}", tracklist.title, spinner(app.spinner_frame))
+    } else {
+        tracklist.title.clone()
+    };
+    let title = cache_status_title(title, tracklist.stale, app);
+    let currently_rating = app.currently_rating;
+
+    let Some(tracklist) = app
+        .releases
+        .as_mut()
+        .and_then(|discography| discography.tracklist.as_mut())
+    else {
         return;
     };
 
-    let selected_index = rated.state.selected().unwrap();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(title)
+        .title_style(Style::default().cyan().bold());
+
+    if tracklist.list.items.is_empty() {
+        let text = if tracklist.loading {
+            ""
+        } else {
+            "No tracks were found"
+        };
+        f.render_widget(Paragraph::new(text).block(block), area);
+        return;
+    }
+
+    let selected_index = tracklist.list.selected();
+
+    let list_items = tracklist
+        .list
+        .items
+        .iter()
+        .enumerate()
+        .map(|(idx, track)| {
+            Line::from(rating_line(
+                &track.to_string(),
+                tracklist.list.matched_indices(idx),
+                track.rating,
+                None,
+                Some(idx) == selected_index,
+                currently_rating,
+            ))
+        })
+        .map(ListItem::new)
+        .collect::<Vec<ListItem>>();
+
+    let list = List::new(list_items).block(block);
+    f.render_stateful_widget(list, area, &mut tracklist.list.state);
+}
+
+pub fn render_ratings(f: &mut Frame, app: &mut App, area: Rect) {
+    let rated = &mut app.rated_list;
+
+    let selected_index = rated.state.selected();
     let mut list_items = Vec::new();
 
     for (idx, item) in rated.items.iter().enumerate() {
-        list_items.push(ListItem::new(Line::from(rating_line(
+        let mut line = rating_line(
             &item.to_string(),
+            rated.matched_indices(idx),
             item.rating,
-            idx == selected_index,
+            item.community,
+            Some(idx) == selected_index,
             app.currently_rating,
-        ))));
+        );
+        line.extend(track_average(item.track_average));
+        line.push(Span::styled(
+            format!(" {}", item.group_type),
+            Style::default().fg(Color::Green).dim(),
+        ));
+        line.extend(tags(&item.tags));
+
+        list_items.push(ListItem::new(Line::from(line)));
+    }
+
+    let mut title = format!(
+        "Ratings ({}/{}) by {}",
+        rated.items.len(),
+        app.rated_total,
+        app.rating_sort
+    );
+
+    if !app.rating_filter.is_empty() {
+        title.push_str(&format!(" · {}", app.rating_filter));
     }
 
     let list = List::new(list_items).block(
         Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .title("Ratings")
+            .title(title)
             .title_style(Style::default().fg(Color::Cyan).bold()),
     );
-    f.render_stateful_widget(list, f.area(), &mut rated.state);
+    f.render_stateful_widget(list, area, &mut rated.state);
+}
+
+fn render_review_editor(f: &mut Frame, app: &App, area: Rect) {
+    let Some(editor) = &app.review_editor else {
+        return;
+    };
+
+    let [_, area, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Percentage(60),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Percentage(80),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+
+    let width = area.width.saturating_sub(2) as usize;
+    let mut rows = wrap_text(&editor.text, width);
+
+    // A full last row leaves the cursor at the start of the next one.
+    if rows.last().is_some_and(|row| row.chars().count() >= width) {
+        rows.push(String::new());
+    }
+
+    let scroll = (rows.len() as u16).saturating_sub(area.height.saturating_sub(2));
+    let cursor = (
+        area.x + 1 + rows.last().map_or(0, |row| row.chars().count()) as u16,
+        area.y + rows.len() as u16 - scroll,
+    );
+
+    let text = Paragraph::new(rows.into_iter().map(Line::from).collect::<Vec<Line>>())
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(format!("Review of {}", editor.description))
+                .title_style(Style::default().cyan().bold())
+                .title_bottom(
+                    Line::from(" Ctrl-S: save  Ctrl-E: open in $EDITOR  Esc: cancel ").dim(),
+                ),
+        );
+
+    f.render_widget(Clear, area);
+    f.render_widget(text, area);
+    f.set_cursor_position(cursor);
+}
+
+/// Breaks every line of the text into rows of at most `width` characters.
+/// Unlike word wrapping, this keeps the position of the cursor predictable.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+
+    for line in text.split('\n') {
+        let chars = line.chars().collect::<Vec<char>>();
+
+        if chars.is_empty() {
+            rows.push(String::new());
+        } else {
+            rows.extend(chars.chunks(width.max(1)).map(String::from_iter));
+        }
+    }
+
+    rows
+}
+
+fn render_details(f: &mut Frame, app: &App, area: Rect) {
+    let Some(release) = app.selected_release() else {
+        render_history(f, app, area);
+        return;
+    };
+
+    let [details_area, history_area] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Percentage(45)]).areas(area);
+
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{name:<14}"), Style::default().dim()),
+            Span::raw(value),
+        ])
+    };
+
+    let secondary = release.group_type.secondary_type_names();
+    let mut lines = vec![
+        Line::from(release.title.clone()).bold(),
+        Line::from(release.id.clone()).dim(),
+        field(
+            "Released",
+            release
+                .first_release_date
+                .clone()
+                .or_else(|| release.known_year().map(|year| year.to_string()))
+                .unwrap_or_else(|| "Unknown".to_string()),
+        ),
+        field(
+            "Type",
+            release
+                .group_type
+                .primary_type()
+                .unwrap_or_else(|| "Unknown".to_string()),
+        ),
+        field(
+            "Secondary",
+            if secondary.is_empty() {
+                "None".to_string()
+            } else {
+                secondary.join(", ")
+            },
+        ),
+        field(
+            "Rating",
+            release
+                .rating
+                .map_or("Not rated".to_string(), |rating| format!("{rating}/10")),
+        ),
+        field(
+            "Our tags",
+            if app.tags.is_empty() {
+                "None".to_string()
+            } else {
+                join_tags(&app.tags)
+            },
+        ),
+        Line::default(),
+    ];
+
+    let mut stale = false;
+
+    match app.details.get(&release.id) {
+        Some(Details::Loaded {
+            info,
+            stale: is_stale,
+        }) => {
+            stale = *is_stale;
+
+            let list = |items: &[String]| {
+                if items.is_empty() {
+                    "None".to_string()
+                } else {
+                    items.join(", ")
+                }
+            };
+
+            lines.extend([
+                field(
+                    "Releases",
+                    format!("{} ({} editions)", info.release_count, info.editions),
+                ),
+                field(
+                    "Community",
+                    info.community
+                        .map_or("No votes".to_string(), |community| community.to_string()),
+                ),
+                field("Genres", list(&info.genres)),
+                field("Tags", list(&info.tags)),
+            ]);
+        }
+        Some(Details::Loading) | None => {
+            lines.push(Line::from(spinner(app.spinner_frame).to_string()).yellow());
+        }
+        Some(Details::Unavailable) => {
+            lines.push(Line::from("Details aren't available").dim());
+
+            // Rated releases have their genres and tags stored.
+            if !release.genres.is_empty() {
+                lines.push(field("Genres", release.genres.join(", ")));
+            }
+
+            if !release.community_tags.is_empty() {
+                lines.push(field("Tags", release.community_tags.join(", ")));
+            }
+        }
+    }
+
+    if !app.artist_genres.is_empty() {
+        lines.push(field("Artist genres", app.artist_genres.join(", ")));
+    }
+
+    let details = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(cache_status_title("Details".to_string(), stale, app))
+            .title_style(Style::default().cyan().bold()),
+    );
+    f.render_widget(details, details_area);
+
+    render_history(f, app, history_area);
+}
+
+fn render_history(f: &mut Frame, app: &App, area: Rect) {
+    let area = match &app.review {
+        Some(review) => {
+            let [review_area, area] =
+                Layout::vertical([Constraint::Percentage(50), Constraint::Fill(1)]).areas(area);
+
+            let review = Paragraph::new(review.as_str())
+                .wrap(Wrap { trim: false })
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .title("Review")
+                        .title_style(Style::default().cyan().bold()),
+                );
+            f.render_widget(review, review_area);
+
+            area
+        }
+        None => area,
+    };
+
+    let events = app
+        .history
+        .iter()
+        .rev()
+        .map(|event| {
+            Line::from(vec![
+                Span::styled(event.date(), Style::default().dim()),
+                Span::raw(format!(" {:>7}", event.change())),
+            ])
+        })
+        .map(ListItem::new)
+        .collect::<Vec<ListItem>>();
+
+    let history = List::new(events).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title("History")
+            .title_style(Style::default().cyan().bold()),
+    );
+
+    f.render_widget(history, area);
 }
 
+pub fn render_review(f: &mut Frame, review: &mut Review) {
+    let [row_area, candidates_area, help_area] = Layout::vertical([
+        Constraint::Length(4),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(f.area());
+
+    let title = format!("Review {}/{}", review.current + 1, review.queue.len());
+
+    let Some(item) = review.current_item() else {
+        return;
+    };
+
+    let artist = match (&item.artist, &item.error) {
+        (_, Some(e)) => Line::from(format!("Couldn't look up the row: {e}")).red(),
+        (Some(artist), None) => Line::from(format!("Matched artist: {artist}")),
+        (None, None) => Line::from("No artist was found").red(),
+    };
+
+    let mut row = vec![Span::raw("RYM: ")];
+    row.extend(rating_line(
+        &item.row.to_string(),
+        &[],
+        Some(item.row.rating),
+        None,
+        false,
+        false,
+    ));
+
+    let row = Paragraph::new(vec![Line::from(row), artist]).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(title)
+            .title_style(Style::default().cyan().bold()),
+    );
+    f.render_widget(row, row_area);
+
+    let candidates = item
+        .candidates
+        .items
+        .iter()
+        .map(|release| {
+            Line::from(vec![
+                Span::raw(release.to_string()),
+                Span::styled(
+                    format!(" {}", release.group_type),
+                    Style::default().fg(Color::Green).dim(),
+                ),
+            ])
+        })
+        .map(ListItem::new)
+        .collect::<Vec<ListItem>>();
+
+    let candidates = List::new(candidates)
+        .highlight_symbol("> ")
+        .highlight_style(
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title("Candidates")
+                .title_style(Style::default().cyan().bold()),
+        );
+    f.render_stateful_widget(candidates, candidates_area, &mut item.candidates.state);
+
+    f.render_widget(
+        Line::from(
+            " Enter: rate the selected release group  s: skip  r: look up again  q: stop reviewing",
+        )
+        .dim(),
+        help_area,
+    );
+}
+
+pub fn render_backlog(f: &mut Frame, app: &mut App, area: Rect) {
+    let backlog = &mut app.backlog;
+    let selected_index = backlog.selected();
+
+    let list_items = backlog
+        .items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| {
+            let mut line = rating_line(
+                &item.to_string(),
+                backlog.matched_indices(idx),
+                item.rating,
+                None,
+                Some(idx) == selected_index,
+                app.currently_rating,
+            );
+            line.push(Span::styled(
+                format!(" {}", item.group_type),
+                Style::default().fg(Color::Green).dim(),
+            ));
+            line.extend(tags(&item.tags));
+            line.push(Span::styled(
+                format!(" added {}", item.date_added()),
+                Style::default().dim(),
+            ));
+
+            ListItem::new(Line::from(line))
+        })
+        .collect::<Vec<ListItem>>();
+
+    let list = List::new(list_items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!("Backlog (by {})", app.backlog_sort.name()))
+            .title_style(Style::default().cyan().bold()),
+    );
+    f.render_stateful_widget(list, area, &mut backlog.state);
+}
+
+pub fn render_stats(f: &mut Frame, app: &App, area: Rect) {
+    let stats = &app.stats;
+
+    let Some(average) = stats.average else {
+        f.render_widget(
+            Paragraph::new("Nothing is rated yet").block(stats_block("Stats".to_string())),
+            area,
+        );
+        return;
+    };
+
+    let [top_area, bottom_area] =
+        Layout::vertical([Constraint::Percentage(50), Constraint::Fill(1)]).areas(area);
+    let [distribution_area, types_area, genres_area] =
+        Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(top_area);
+    let [decades_area, artists_area, releases_area] =
+        Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(bottom_area);
+
+    let distribution = stats
+        .distribution
+        .iter()
+        .enumerate()
+        .map(|(idx, count)| {
+            Bar::default()
+                .value(*count as u64)
+                .label(Line::from((idx + 1).to_string()))
+        })
+        .collect::<Vec<Bar>>();
+    // Ten bars with a gap of one between them, filling the block.
+    let bar_width = (distribution_area.width.saturating_sub(2 + 9) / 10).max(1);
+
+    f.render_widget(
+        BarChart::default()
+            .block(stats_block(if app.rating_filter.is_empty() {
+                format!("Ratings ({}, ⌀ {average:.2})", stats.total)
+            } else {
+                format!(
+                    "Ratings ({}, ⌀ {average:.2}) · {}",
+                    stats.total, app.rating_filter
+                )
+            }))
+            .data(BarGroup::default().bars(&distribution))
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(Color::Yellow))
+            .value_style(Style::default().fg(Color::Black).bg(Color::Yellow)),
+        distribution_area,
+    );
+
+    let types = stats
+        .release_types
+        .iter()
+        .map(|release_type| {
+            Bar::default()
+                .value(release_type.count as u64)
+                .label(Line::from(release_type.release_type.clone()))
+        })
+        .collect::<Vec<Bar>>();
+
+    f.render_widget(
+        horizontal_bar_chart(&types, "Release Types".to_string()),
+        types_area,
+    );
+
+    let decades = stats
+        .decades
+        .iter()
+        .map(|decade| {
+            Bar::default()
+                .value(decade.count as u64)
+                .label(Line::from(format!("{}s", decade.decade)))
+                .text_value(format!("{} ⌀ {:.1}", decade.count, decade.average))
+        })
+        .collect::<Vec<Bar>>();
+
+    f.render_widget(
+        horizontal_bar_chart(&decades, "Decades".to_string()),
+        decades_area,
+    );
+
+    // Averages are scaled so that the bars can be compared against a 10.
+    let artists = stats
+        .top_artists
+        .iter()
+        .map(|artist| {
+            Bar::default()
+                .value((artist.average * 100.0).round() as u64)
+                .label(Line::from(artist.artist_name.clone()))
+                .text_value(format!("{:.2} ({})", artist.average, artist.count))
+        })
+        .collect::<Vec<Bar>>();
+
+    f.render_widget(
+        horizontal_bar_chart(
+            &artists,
+            format!(
+                "Top Artists (at least {} ratings)",
+                stats::DEFAULT_MIN_RATINGS
+            ),
+        )
+        .max(1000),
+        artists_area,
+    );
+
+    let genres = stats
+        .genres
+        .iter()
+        .map(|genre| {
+            Bar::default()
+                .value(genre.count as u64)
+                .label(Line::from(genre.genre.clone()))
+                .text_value(format!("{} ⌀ {:.1}", genre.count, genre.average))
+        })
+        .collect::<Vec<Bar>>();
+
+    f.render_widget(
+        horizontal_bar_chart(&genres, "Genres".to_string()),
+        genres_area,
+    );
+
+    let releases = stats
+        .top_releases
+        .iter()
+        .map(|release| {
+            Line::from(vec![
+                Span::styled(format!("{:>2} ", release.rating), Style::default().yellow()),
+                Span::raw(format!("{} - {}", release.artist_name, release.title)),
+            ])
+        })
+        .collect::<Vec<Line>>();
+
+    f.render_widget(
+        Paragraph::new(releases).block(stats_block("Top Releases".to_string())),
+        releases_area,
+    );
+}
+
+fn horizontal_bar_chart<'a>(bars: &'a [Bar<'a>], title: String) -> BarChart<'a> {
+    BarChart::default()
+        .block(stats_block(title))
+        .direction(Direction::Horizontal)
+        .data(BarGroup::default().bars(bars))
+        .bar_width(1)
+        .bar_gap(0)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+}
+
+fn stats_block(title: String) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(title)
+        .title_style(Style::default().cyan().bold())
+}
+
+fn spinner(frame: usize) -> char {
+    const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+    FRAMES[frame % FRAMES.len()]
+}
+
+/// Marks lists that were served from an expired cache entry, and whether
+/// MusicBrainz is being queried at all.
+fn cache_status_title(title: String, stale: bool, app: &App) -> Line<'static> {
+    let mut spans = vec![Span::raw(title)];
+
+    if stale {
+        spans.push(Span::styled(" [stale]", Style::default().fg(Color::Yellow)));
+    }
+
+    if app.cache.offline {
+        spans.push(Span::styled(" [offline]", Style::default().fg(Color::Red)));
+    }
+
+    Line::from(spans)
+}
+
+/// Our tags of a release, if it has any.
+fn tags(tags: &[String]) -> Option<Span<'static>> {
+    (!tags.is_empty()).then(|| {
+        Span::styled(
+            format!(" [{}]", join_tags(tags)),
+            Style::default().fg(Color::Blue),
+        )
+    })
+}
+
+/// The average rating of the rated tracks of a release group.
+fn track_average(average: Option<f64>) -> Option<Span<'static>> {
+    average.map(|average| {
+        Span::styled(
+            format!(" ⌀ {average:.1}"),
+            Style::default().fg(Color::Cyan).dim(),
+        )
+    })
+}
+
+/// The line of a rated item. The community rating, if there is one, is shown
+/// after the stars along with how far our rating is from it.
 pub fn rating_line(
     line: &str,
+    matched: &[usize],
     rating: Option<u8>,
+    community: Option<CommunityRating>,
     selected: bool,
     currently_rating: bool,
 ) -> Vec<Span<'static>> {
@@ -191,22 +1355,22 @@ pub fn rating_line(
         stars_filler.push_str(&"★ ".repeat((10 - stars.chars().count()) / 2));
     }
 
-    vec![
-        Span::styled(format!("{} {} ", if selected { ">" } else { " " }, line), {
-            let mut style = Style::default();
+    let mut style = Style::default();
 
-            if selected {
-                style = style
-                    .fg(if currently_rating {
-                        Color::Blue
-                    } else {
-                        Color::Magenta
-                    })
-                    .bold();
-            }
+    if selected {
+        style = style
+            .fg(if currently_rating {
+                Color::Blue
+            } else {
+                Color::Magenta
+            })
+            .bold();
+    }
 
-            style
-        }),
+    let mut spans = vec![Span::styled(if selected { "> " } else { "  " }, style)];
+    spans.extend(highlight_matches(line, matched, style));
+    spans.extend([
+        Span::styled(" ", style),
         Span::styled(
             stars,
             Style::default()
@@ -214,5 +1378,135 @@ pub fn rating_line(
                 .add_modifier(Modifier::BOLD),
         ),
         Span::styled(stars_filler, Style::default().bold()),
-    ]
+    ]);
+
+    if let Some(community) = community {
+        spans.push(Span::styled(
+            format!(" MB {:.1} ({})", community.value, community.votes),
+            Style::default().dim(),
+        ));
+
+        if let Some(rating) = rating {
+            spans.push(difference(community.difference(rating)));
+        }
+    }
+
+    spans
+}
+
+/// Our rating minus the community rating, green when we liked the release
+/// more than the community did.
+fn difference(difference: f32) -> Span<'static> {
+    let color = if difference >= 0.0 {
+        Color::Green
+    } else {
+        Color::Red
+    };
+
+    Span::styled(format!(" {difference:+.1}"), Style::default().fg(color))
+}
+
+/// Splits the text into spans, highlighting the characters that the fuzzy
+/// filter matched.
+pub fn highlight_matches(text: &str, matched: &[usize], style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+
+    for (idx, c) in text.chars().enumerate() {
+        let is_matched = matched.contains(&idx);
+
+        if is_matched != run_matched && !run.is_empty() {
+            spans.push(match_span(std::mem::take(&mut run), run_matched, style));
+        }
+
+        run_matched = is_matched;
+        run.push(c);
+    }
+
+    if !run.is_empty() {
+        spans.push(match_span(run, run_matched, style));
+    }
+
+    spans
+}
+
+fn match_span(text: String, matched: bool, style: Style) -> Span<'static> {
+    if matched {
+        Span::styled(text, style.fg(Color::Yellow).underlined())
+    } else {
+        Span::styled(text, style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item(&'static str);
+
+    impl Searchable for Item {
+        fn search_text(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn is_header(&self) -> bool {
+            self.0.starts_with('#')
+        }
+    }
+
+    fn list(names: &[&'static str]) -> StatefulList<Item> {
+        StatefulList::with_items(names.iter().map(|name| Item(name)).collect())
+    }
+
+    fn shown(list: &StatefulList<Item>) -> Vec<&str> {
+        list.items.iter().map(|item| item.0).collect()
+    }
+
+    #[test]
+    fn filters_and_restores_the_items_in_order() {
+        let mut list = list(&["alpha", "beta", "gamma", "delta"]);
+
+        list.set_query("ta");
+        assert_eq!(shown(&list), ["beta", "delta"]);
+        assert_eq!(list.position(1), 3);
+
+        list.set_query("");
+        assert_eq!(shown(&list), ["alpha", "beta", "gamma", "delta"]);
+        assert_eq!(list.position(1), 1);
+    }
+
+    #[test]
+    fn keeps_the_selection_on_the_same_item() {
+        let mut list = list(&["alpha", "beta", "gamma", "delta"]);
+        list.select(Some(3));
+
+        list.set_query("ta");
+        assert_eq!(list.get_selected().map(|item| item.0), Some("delta"));
+
+        list.set_query("");
+        assert_eq!(list.get_selected().map(|item| item.0), Some("delta"));
+    }
+
+    #[test]
+    fn keeps_headers_of_matching_items() {
+        let mut list = list(&["#Album", "alpha", "beta", "#Live", "gamma"]);
+
+        list.set_query("bet");
+        assert_eq!(shown(&list), ["#Album", "beta"]);
+        assert_eq!(list.selected(), Some(1));
+    }
+
+    #[test]
+    fn applies_the_query_to_new_items() {
+        let mut list = list(&["alpha", "beta"]);
+        list.set_query("ga");
+
+        let mut items = list.take_items();
+        items.push(Item("gamma"));
+        list.set_items(items);
+
+        assert_eq!(shown(&list), ["gamma"]);
+        assert_eq!(list.iter_all().count(), 3);
+    }
 }
@@ -1,4 +1,5 @@
-use anyhow::{bail, Result};
+use anyhow::{Result, bail};
+use musicbrainz_rs::chrono::{DateTime, Local, NaiveDate};
 use std::path::PathBuf;
 
 const APP_NAME: &str = "rambt";
@@ -19,3 +20,80 @@ fn get_data_dir() -> Result<PathBuf> {
 pub fn get_database_path() -> Result<PathBuf> {
     Ok(get_data_dir()?.join("ratings.db"))
 }
+
+pub fn get_cache_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("cache.db"))
+}
+
+pub fn get_covers_dir() -> Result<PathBuf> {
+    let path = get_data_dir()?.join("covers");
+
+    if !path.exists() {
+        std::fs::create_dir_all(&path)?;
+    }
+
+    Ok(path)
+}
+
+/// Checks whether the given string is a MusicBrainz identifier, which is a
+/// UUID in its hyphenated form.
+pub fn is_mbid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(idx, c)| match idx {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Formats a Unix timestamp as a date and time in the local time zone.
+pub fn format_timestamp(timestamp: i64) -> String {
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(time) => time
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date into the Unix timestamp of its local midnight.
+pub fn parse_date(value: &str) -> Option<i64> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|time| time.timestamp())
+}
+
+/// Parses tags written as `road trip, revisit`, dropping empty ones and the
+/// ones that are repeated in another case.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags = Vec::<String>::new();
+
+    for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|other| other.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    tags
+}
+
+pub fn join_tags(tags: &[String]) -> String {
+    tags.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!(
+            parse_tags(" road trip, revisit,, Road Trip ,"),
+            ["road trip", "revisit"]
+        );
+        assert!(parse_tags(" , ").is_empty());
+    }
+}